@@ -0,0 +1,402 @@
+use std::{cmp::Ordering, fmt};
+
+// The most bits a power may have, a larger one would take too long to compute
+const MAX_POWER_BITS: u64 = 1 << 18;
+
+// An integer of any size, what i64 arithmetic promotes to when it overflows. The magnitude is
+// kept in base 2^32 digits, least significant first, without leading zero digits, so each
+// value has exactly one representation and the derived equality and hashing are by value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    // Zero has no digits and is never negative
+    digits: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self { negative: false, digits: Vec::new() }
+    }
+
+    fn from_parts(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        let negative: bool = negative && !digits.is_empty();
+        Self { negative, digits }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_parts(false, self.digits.clone())
+    }
+
+    // The greatest common divisor of the magnitudes, zero only when both are
+    pub fn gcd(&self, other: &Self) -> Self {
+        let (mut left, mut right) = (self.abs(), other.abs());
+        while !right.is_zero() {
+            let (_, remainder) = left.divide(&right).unwrap();
+            left = std::mem::replace(&mut right, remainder);
+        }
+        left
+    }
+
+    // Digits in 'radix' without a sign or separators, None on anything else
+    pub fn parse(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut magnitude: Vec<u32> = Vec::new();
+        for character in digits.chars() {
+            let digit: u32 = character.to_digit(radix)?;
+            magnitude = Self::multiply_small(&magnitude, radix, digit);
+        }
+        Some(Self::from_parts(false, magnitude))
+    }
+
+    // The value when it fits in an i64
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+
+        let magnitude: i128 = self.digits.iter().rev().fold(0_i128, |value, digit| (value << 32) | *digit as i128);
+        let value: i128 = if self.negative { -magnitude } else { magnitude };
+        i64::try_from(value).ok()
+    }
+
+    // The nearest float, or infinity past the float range
+    pub fn to_f64(&self) -> f64 {
+        let magnitude: f64 = self.digits.iter().rev().fold(0.0, |value, digit| value * 4294967296.0 + *digit as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    // Whole, finite floats convert exactly
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.fract() != 0.0 {
+            return None;
+        }
+
+        // A whole float is its 53 bit mantissa shifted left by the exponent
+        let bits: u64 = value.to_bits();
+        let exponent: i64 = ((bits >> 52) & 0x7ff) as i64;
+        if exponent == 0 {
+            return Some(Self::zero());
+        }
+        let mantissa: u64 = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let shift: i64 = exponent - 1075;
+
+        let magnitude: BigInt = if shift < 0 {
+            Self::from(mantissa >> -shift)
+        } else {
+            Self::from(mantissa).shift_left(shift as usize)
+        };
+        Some(Self::from_parts(value < 0.0, magnitude.digits))
+    }
+
+    pub fn shift_left(&self, bits: usize) -> Self {
+        let mut digits: Vec<u32> = vec![0; bits / 32];
+        let offset: u32 = (bits % 32) as u32;
+
+        let mut carry: u32 = 0;
+        for digit in &self.digits {
+            if offset == 0 {
+                digits.push(*digit);
+            } else {
+                digits.push((digit << offset) | carry);
+                carry = digit >> (32 - offset);
+            }
+        }
+        digits.push(carry);
+        Self::from_parts(self.negative, digits)
+    }
+
+    pub fn negate(&self) -> Self {
+        Self::from_parts(!self.negative, self.digits.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return Self::from_parts(self.negative, Self::add_magnitudes(&self.digits, &other.digits));
+        }
+
+        // Different signs, the larger magnitude decides the sign
+        match Self::compare_magnitudes(&self.digits, &other.digits) {
+            Ordering::Less => Self::from_parts(other.negative, Self::subtract_magnitudes(&other.digits, &self.digits)),
+            _ => Self::from_parts(self.negative, Self::subtract_magnitudes(&self.digits, &other.digits)),
+        }
+    }
+
+    pub fn subtract(&self, other: &Self) -> Self {
+        self.add(&other.negate())
+    }
+
+    pub fn multiply(&self, other: &Self) -> Self {
+        let mut digits: Vec<u32> = vec![0; self.digits.len() + other.digits.len()];
+        for (i, left) in self.digits.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (j, right) in other.digits.iter().enumerate() {
+                let product: u64 = *left as u64 * *right as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        Self::from_parts(self.negative != other.negative, digits)
+    }
+
+    // The quotient rounded toward zero and the remainder with the sign of 'self', None for a zero divisor
+    pub fn divide(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = Self::divide_magnitudes(&self.digits, &other.digits);
+        Some((Self::from_parts(self.negative != other.negative, quotient), Self::from_parts(self.negative, remainder)))
+    }
+
+    // 'exponent' isn't negative, None when the result would have more than MAX_POWER_BITS bits
+    pub fn pow(&self, exponent: &Self) -> Option<Self> {
+        // Zero, one and minus one stay that small whatever the exponent
+        match self.digits.as_slice() {
+            [] if exponent.is_zero() => return Some(Self::from(1_u64)),
+            [] => return Some(Self::zero()),
+            [1] if self.negative && exponent.digits.first().is_some_and(|digit| digit & 1 == 1) => return Some(self.clone()),
+            [1] => return Some(Self::from(1_u64)),
+            _ => {},
+        }
+
+        // The power has at most this many bits
+        let mut exponent: u64 = u64::try_from(exponent.to_i64()?).ok()?;
+        if self.bits().checked_mul(exponent)? > MAX_POWER_BITS {
+            return None;
+        }
+
+        let mut result: BigInt = Self::from(1_u64);
+        let mut base: BigInt = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.multiply(&base);
+            }
+        }
+        Some(result)
+    }
+
+    // The number of bits in the magnitude
+    fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(last) => self.digits.len() as u64 * 32 - last.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    fn add_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+        let mut digits: Vec<u32> = Vec::with_capacity(left.len().max(right.len()) + 1);
+        let mut carry: u64 = 0;
+        for i in 0..left.len().max(right.len()) {
+            let sum: u64 = *left.get(i).unwrap_or(&0) as u64 + *right.get(i).unwrap_or(&0) as u64 + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        digits
+    }
+
+    // 'left' must not be smaller than 'right'
+    fn subtract_magnitudes(left: &[u32], right: &[u32]) -> Vec<u32> {
+        let mut digits: Vec<u32> = Vec::with_capacity(left.len());
+        let mut borrow: i64 = 0;
+        for (i, digit) in left.iter().enumerate() {
+            let mut difference: i64 = *digit as i64 - *right.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(difference as u32);
+        }
+        digits
+    }
+
+    fn compare_magnitudes(left: &[u32], right: &[u32]) -> Ordering {
+        left.len().cmp(&right.len()).then_with(|| left.iter().rev().cmp(right.iter().rev()))
+    }
+
+    // magnitude * factor + addend
+    fn multiply_small(magnitude: &[u32], factor: u32, addend: u32) -> Vec<u32> {
+        let mut digits: Vec<u32> = Vec::with_capacity(magnitude.len() + 1);
+        let mut carry: u64 = addend as u64;
+        for digit in magnitude {
+            let product: u64 = *digit as u64 * factor as u64 + carry;
+            digits.push(product as u32);
+            carry = product >> 32;
+        }
+        digits.push(carry as u32);
+        digits
+    }
+
+    fn divide_small(magnitude: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+        let mut quotient: Vec<u32> = vec![0; magnitude.len()];
+        let mut remainder: u64 = 0;
+        for (i, digit) in magnitude.iter().enumerate().rev() {
+            let current: u64 = (remainder << 32) | *digit as u64;
+            quotient[i] = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        (quotient, remainder as u32)
+    }
+
+    // Long division a bit at a time, slow for huge divisors but simple
+    fn divide_magnitudes(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+        if divisor.len() == 1 {
+            let (quotient, remainder) = Self::divide_small(dividend, divisor[0]);
+            return (quotient, vec![remainder]);
+        }
+        if Self::compare_magnitudes(dividend, divisor) == Ordering::Less {
+            return (Vec::new(), dividend.to_vec());
+        }
+
+        let mut quotient: Vec<u32> = vec![0; dividend.len()];
+        let mut remainder: BigInt = Self::zero();
+        for bit in (0..dividend.len() * 32).rev() {
+            remainder = remainder.shift_left(1);
+            if (dividend[bit / 32] >> (bit % 32)) & 1 == 1 {
+                if remainder.digits.is_empty() {
+                    remainder.digits.push(0);
+                }
+                remainder.digits[0] |= 1;
+            }
+            if Self::compare_magnitudes(&remainder.digits, divisor) != Ordering::Less {
+                remainder = Self::from_parts(false, Self::subtract_magnitudes(&remainder.digits, divisor));
+                quotient[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+        (quotient, remainder.digits)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude: BigInt = Self::from(value.unsigned_abs());
+        Self::from_parts(value < 0, magnitude.digits)
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(value: u64) -> Self {
+        Self::from_parts(false, vec![value as u32, (value >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => Self::compare_magnitudes(&self.digits, &other.digits),
+            (true, true) => Self::compare_magnitudes(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Nine decimal digits at a time, the most significant chunk is printed without padding
+        let mut chunks: Vec<u32> = Vec::new();
+        let mut magnitude: Vec<u32> = self.digits.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = Self::divide_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = Self::from_parts(false, quotient).digits;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod bigint_tests {
+    use crate::bigint::BigInt;
+
+    fn big(digits: &str) -> BigInt {
+        match digits.strip_prefix('-') {
+            Some(digits) => BigInt::parse(digits, 10).unwrap().negate(),
+            None => BigInt::parse(digits, 10).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_print() {
+        assert!("0" == big("0").to_string());
+        assert!("123456789012345678901234567890" == big("123456789012345678901234567890").to_string());
+        assert!("-1000000000000000000000" == big("-1000000000000000000000").to_string());
+        assert!("18446744073709551616" == BigInt::parse("10000000000000000", 16).unwrap().to_string());
+        assert!(BigInt::parse("12a", 10).is_none());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a: BigInt = big("123456789012345678901234567890");
+        let b: BigInt = big("-987654321098765432109876543210");
+        assert!("-864197532086419753208641975320" == a.add(&b).to_string());
+        assert!("1111111110111111111011111111100" == a.subtract(&b).to_string());
+        assert!("-121932631137021795226185032733622923332237463801111263526900" == a.multiply(&b).to_string());
+
+        let (quotient, remainder) = b.divide(&a).unwrap();
+        assert!("-8" == quotient.to_string());
+        assert!("-9000000000900000000090" == remainder.to_string());
+        assert!(a.divide(&BigInt::zero()).is_none());
+        assert!(Some(big("1267650600228229401496703205376")) == BigInt::from(2_i64).pow(&big("100")));
+    }
+
+    #[test]
+    fn test_pow_is_bounded() {
+        let huge: BigInt = big("10000000000000000000000");
+        assert!(BigInt::from(2_i64).pow(&big("4000000000")).is_none());
+        assert!(BigInt::from(2_i64).pow(&huge).is_none());
+        assert!(Some(big("1")) == big("1").pow(&huge));
+        assert!(Some(big("-1")) == big("-1").pow(&big("10000000001")));
+        assert!(Some(big("1")) == big("-1").pow(&huge));
+        assert!(Some(BigInt::zero()) == BigInt::zero().pow(&huge));
+        assert!(Some(big("1")) == BigInt::zero().pow(&BigInt::zero()));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert!(Some(i64::MIN) == BigInt::from(i64::MIN).to_i64());
+        assert!(BigInt::from(i64::MAX).add(&BigInt::from(1_i64)).to_i64().is_none());
+        assert!(big("100000000000000000000") == BigInt::from_f64(1e20).unwrap());
+        assert!(1e20 == big("100000000000000000000").to_f64());
+        assert!(BigInt::from_f64(0.5).is_none());
+        assert!(big("-5") < big("3") && big("-5") < big("-4") && big("10000000000000") > big("9999999999999"));
+    }
+}
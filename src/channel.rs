@@ -0,0 +1,67 @@
+use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
+
+use crate::{fiber::Wait, function::NativeFunction, object::Object, token::Token};
+
+// An unbuffered channel, a send waits until a receiver has taken its value. Values offered by
+// waiting senders are taken in the order they were sent.
+#[derive(Default)]
+pub struct Channel {
+    offers: VecDeque<(usize, Object)>,
+    next_offer: usize,
+}
+
+impl Channel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Puts up a value for the next receiver, the id tells the sender when it has been taken
+    pub fn offer(&mut self, value: Object) -> usize {
+        let id: usize = self.next_offer;
+        self.next_offer += 1;
+        self.offers.push_back((id, value));
+        id
+    }
+
+    pub fn is_taken(&self, offer: usize) -> bool {
+        !self.offers.iter().any(|(id, _)| *id == offer)
+    }
+
+    // The oldest value on offer, None when no sender is waiting
+    pub fn take(&mut self) -> Option<Object> {
+        self.offers.pop_front().map(|(_, value)| value)
+    }
+
+    pub fn method(channel: &Rc<RefCell<Channel>>, name: &Token) -> Option<NativeFunction> {
+        let channel: Rc<RefCell<Channel>> = channel.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "send" => NativeFunction::new("send", 1, Rc::new(move |interpreter, paren, mut arguments| {
+                // Nothing takes the value while the main program waits, only fibers take turns
+                interpreter.check_give_way(paren, "Only a fiber can wait to send.")?;
+                let offer: usize = channel.borrow_mut().offer(arguments.remove(0));
+                interpreter.give_way(Wait::Send(channel.clone(), offer));
+                Ok(Object::Null)
+            })),
+            "receive" => NativeFunction::new("receive", 0, Rc::new(move |interpreter, paren, _| {
+                let value: Option<Object> = channel.borrow_mut().take();
+                if let Some(value) = value {
+                    return Ok(value);
+                }
+
+                // The fiber gets the value from its wait once a sender offers one
+                interpreter.check_give_way(paren, "Channel is empty, only a fiber can wait to receive.")?;
+                interpreter.give_way(Wait::Receive(channel.clone()));
+                Ok(Object::Null)
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+impl fmt::Debug for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<channel>")
+    }
+}
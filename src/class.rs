@@ -0,0 +1,126 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use crate::{function::{Function, NativeFunction}, object::Object};
+
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<Function>>,
+    // Static methods, they are never bound to an instance
+    pub class_methods: HashMap<String, Rc<Function>>,
+    pub getters: HashMap<String, Rc<Function>>,
+    pub setters: HashMap<String, Rc<Function>>,
+    // Static methods written in Rust, for built-in classes like Fiber
+    pub native_methods: HashMap<String, Rc<NativeFunction>>,
+}
+
+impl Class {
+    pub fn new(name: &str, superclass: Option<Rc<Class>>, methods: HashMap<String, Rc<Function>>) -> Self {
+        Self {
+            name: name.to_string(),
+            superclass,
+            methods,
+            class_methods: HashMap::new(),
+            getters: HashMap::new(),
+            setters: HashMap::new(),
+            native_methods: HashMap::new(),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.methods)
+    }
+
+    pub fn find_class_method(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.class_methods)
+    }
+
+    pub fn find_getter(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.getters)
+    }
+
+    pub fn find_setter(&self, name: &str) -> Option<Rc<Function>> {
+        self.find(name, |class| &class.setters)
+    }
+
+    // Looks in the table picked by 'table' on this class, then up the superclass chain
+    fn find(&self, name: &str, table: fn(&Class) -> &HashMap<String, Rc<Function>>) -> Option<Rc<Function>> {
+        if let Some(method) = table(self).get(name) {
+            return Some(method.clone());
+        }
+
+        self.superclass.as_ref().and_then(|superclass| superclass.find(name, table))
+    }
+
+    // Whether this is 'class' or inherits from it
+    pub fn is_subclass_of(&self, class: &Rc<Class>) -> bool {
+        if std::ptr::eq(self, Rc::as_ptr(class)) {
+            return true;
+        }
+        self.superclass.as_ref().is_some_and(|superclass| superclass.is_subclass_of(class))
+    }
+
+    // Copies in the methods of 'traits' that this class does not define itself. Two traits
+    // providing different methods under one name is a conflict the class must resolve by
+    // defining that method, the error names the first conflict in trait order
+    pub fn mix_in(&mut self, traits: &[Rc<Class>]) -> Result<(), String> {
+        Self::mix_in_table(&mut self.methods, traits, |class| &class.methods)?;
+        Self::mix_in_table(&mut self.class_methods, traits, |class| &class.class_methods)?;
+        Self::mix_in_table(&mut self.getters, traits, |class| &class.getters)?;
+        Self::mix_in_table(&mut self.setters, traits, |class| &class.setters)
+    }
+
+    fn mix_in_table(own: &mut HashMap<String, Rc<Function>>, traits: &[Rc<Class>], table: fn(&Class) -> &HashMap<String, Rc<Function>>) -> Result<(), String> {
+        let mut provided: HashMap<String, (Rc<Function>, &str)> = HashMap::new();
+
+        for mixed in traits {
+            let mut names: Vec<&String> = table(mixed).keys().filter(|name| !own.contains_key(*name)).collect();
+            names.sort();
+
+            for name in names {
+                let method: &Rc<Function> = &table(mixed)[name];
+                match provided.get(name) {
+                    // The same method reached through two traits, like a trait both of them include
+                    Some((other, _)) if Rc::ptr_eq(other, method) => {},
+                    Some((_, other_trait)) => {
+                        return Err(format!("Method '{}' is provided by both '{}' and '{}'.", name, other_trait, mixed.name));
+                    },
+                    None => {
+                        provided.insert(name.clone(), (method.clone(), &mixed.name));
+                    },
+                }
+            }
+        }
+
+        own.extend(provided.into_iter().map(|(name, (method, _))| (name, method)));
+        Ok(())
+    }
+
+    pub fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |initializer| initializer.arity())
+    }
+}
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, Object>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self { class, fields: HashMap::new() }
+    }
+}
+
+// Fields can refer back to the instance, so only the class name is printed
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
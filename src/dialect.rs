@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+// Selects the keyword spellings and language extensions the scanner and parser accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    // Lox exactly as the book describes it
+    Standard,
+    // Standard Lox plus the rlox extensions
+    #[default]
+    Extended,
+    // The extensions with the `null` keyword older rlox scripts are written with
+    Legacy,
+}
+
+impl Dialect {
+    pub const NAMES: [&'static str; 3] = ["standard", "extended", "legacy"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Standard => "standard",
+            Dialect::Extended => "extended",
+            Dialect::Legacy => "legacy",
+        }
+    }
+
+    pub fn nil_keyword(&self) -> &'static str {
+        match self {
+            Dialect::Legacy => "null",
+            _ => "nil",
+        }
+    }
+
+    pub fn has_extensions(&self) -> bool {
+        *self != Dialect::Standard
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "standard" => Ok(Dialect::Standard),
+            "extended" => Ok(Dialect::Extended),
+            "legacy" => Ok(Dialect::Legacy),
+            _ => Err(format!("Unknown dialect '{}', expected one of {}", name, Dialect::NAMES.join(", "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod dialect_tests {
+    use super::Dialect;
+
+    #[test]
+    fn test_dialect_names_round_trip() {
+        for name in Dialect::NAMES {
+            let dialect: Dialect = name.parse().unwrap();
+            assert!(name == dialect.name());
+        }
+    }
+
+    #[test]
+    fn test_unknown_dialect_name() {
+        assert!("lua".parse::<Dialect>().is_err());
+    }
+
+    #[test]
+    fn test_default_dialect_uses_nil() {
+        assert!("nil" == Dialect::default().nil_keyword());
+        assert!(Dialect::default().has_extensions());
+    }
+}
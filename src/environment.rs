@@ -0,0 +1,73 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{interpreter::RuntimeError, object::Object, token::Token};
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Object>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self { values: HashMap::new(), enclosing: Some(enclosing) }
+    }
+
+    pub fn define(&mut self, name: &str, value: Object) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Object, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(Self::undefined(name)),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(Self::undefined(name)),
+        }
+    }
+
+    // The environment 'distance' hops up the chain, as worked out by the resolver
+    pub fn ancestor(environment: &Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut current: Rc<RefCell<Environment>> = environment.clone();
+        for _ in 0..distance {
+            let enclosing: Rc<RefCell<Environment>> = current.borrow().enclosing.clone().expect("Resolved scope is missing.");
+            current = enclosing;
+        }
+        current
+    }
+
+    pub fn get_at(environment: &Rc<RefCell<Environment>>, distance: usize, name: &Token) -> Result<Object, RuntimeError> {
+        Self::ancestor(environment, distance).borrow().get_here(&name.lexeme).ok_or_else(|| Self::undefined(name))
+    }
+
+    pub fn assign_at(environment: &Rc<RefCell<Environment>>, distance: usize, name: &Token, value: Object) {
+        Self::ancestor(environment, distance).borrow_mut().define(&name.lexeme, value);
+    }
+
+    // Reads a variable defined directly in this environment
+    pub fn get_here(&self, name: &str) -> Option<Object> {
+        self.values.get(name).cloned()
+    }
+
+    fn undefined(name: &Token) -> RuntimeError {
+        RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))
+    }
+}
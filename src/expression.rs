@@ -1,41 +1,229 @@
-use std::fmt::Binary;
+use std::{rc::Rc, sync::atomic::{AtomicUsize, Ordering}};
 
-use crate::{object::Object, token::Token};
+use crate::{object::Object, statement::FunctionDeclaration, token::Token};
 
-#[derive(Debug)]
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Unique across every parse, so the REPL can keep resolved scopes from earlier lines
+pub fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone)]
 pub enum Expression {
+    Assign {
+        id: usize,
+        name: Token,
+        value: Box<Self>,
+    },
     Binary {
         left: Box<Self>,
         right: Box<Self>,
         operator: Token,
     },
+    Call {
+        callee: Box<Self>,
+        paren: Token,
+        arguments: Vec<Self>,
+    },
+    // name += value, ++name and name++ are the same with a value of 1, 'postfix' evaluates to the old value
+    CompoundAssign {
+        id: usize,
+        name: Token,
+        operator: Token,
+        value: Box<Self>,
+        postfix: bool,
+    },
+    // object.name += value, like CompoundAssign but for fields
+    CompoundSet {
+        object: Box<Self>,
+        name: Token,
+        operator: Token,
+        value: Box<Self>,
+        postfix: bool,
+    },
+    // object[index] += value, like CompoundAssign but for elements
+    CompoundSetIndex {
+        object: Box<Self>,
+        bracket: Token,
+        index: Box<Self>,
+        operator: Token,
+        value: Box<Self>,
+        postfix: bool,
+    },
+    // condition ? then_branch : else_branch
+    Conditional {
+        condition: Box<Self>,
+        then_branch: Box<Self>,
+        else_branch: Box<Self>,
+    },
+    // fun (params) { body } and (params) => value, the arrow form's body returns the value
+    Function {
+        declaration: Rc<FunctionDeclaration>,
+    },
+    Get {
+        object: Box<Self>,
+        name: Token,
+    },
     Grouping {
         expression: Box<Self>,
     },
+    // object[index], 'bracket' is the closing bracket for error reporting
+    Index {
+        object: Box<Self>,
+        bracket: Token,
+        index: Box<Self>,
+    },
+    // "Hello ${name}!", the parts are string literals and embedded expressions in source order
+    Interpolation {
+        parts: Vec<Self>,
+    },
+    List {
+        elements: Vec<Self>,
+    },
     Literal {
         value: Object,
     },
+    // {key: value, ...}, 'brace' is the closing brace for error reporting
+    Map {
+        brace: Token,
+        entries: Vec<(Self, Self)>,
+    },
+    Logical {
+        left: Box<Self>,
+        right: Box<Self>,
+        operator: Token,
+    },
+    Set {
+        object: Box<Self>,
+        name: Token,
+        value: Box<Self>,
+    },
+    SetIndex {
+        object: Box<Self>,
+        bracket: Token,
+        index: Box<Self>,
+        value: Box<Self>,
+    },
+    Super {
+        id: usize,
+        keyword: Token,
+        method: Token,
+    },
+    This {
+        id: usize,
+        keyword: Token,
+    },
     Unary {
         operator: Token,
         right: Box<Self>,
     },
+    Variable {
+        id: usize,
+        name: Token,
+    },
 }
 
-pub trait ExpressionVisitor {
-    fn visit_binary_expression(&self, expr: &Expression);
-    fn visit_grouping_expression(&self, expr: &Expression);
-    fn visit_literal_expression(&self, expr: &Expression);
-    fn visit_unary_expression(&self, expr: &Expression);
+pub trait ExpressionVisitor<R> {
+    fn visit_assign_expression(&mut self, id: usize, name: &Token, value: &Expression) -> R;
+    fn visit_binary_expression(&mut self, left: &Expression, operator: &Token, right: &Expression) -> R;
+    fn visit_call_expression(&mut self, callee: &Expression, paren: &Token, arguments: &[Expression]) -> R;
+    fn visit_compound_assign_expression(&mut self, id: usize, name: &Token, operator: &Token, value: &Expression, postfix: bool) -> R;
+    fn visit_compound_set_expression(&mut self, object: &Expression, name: &Token, operator: &Token, value: &Expression, postfix: bool) -> R;
+    fn visit_compound_set_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression, operator: &Token, value: &Expression, postfix: bool) -> R;
+    fn visit_conditional_expression(&mut self, condition: &Expression, then_branch: &Expression, else_branch: &Expression) -> R;
+    fn visit_function_expression(&mut self, declaration: &Rc<FunctionDeclaration>) -> R;
+    fn visit_get_expression(&mut self, object: &Expression, name: &Token) -> R;
+    fn visit_grouping_expression(&mut self, expression: &Expression) -> R;
+    fn visit_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression) -> R;
+    fn visit_interpolation_expression(&mut self, parts: &[Expression]) -> R;
+    fn visit_list_expression(&mut self, elements: &[Expression]) -> R;
+    fn visit_literal_expression(&mut self, value: &Object) -> R;
+    fn visit_map_expression(&mut self, brace: &Token, entries: &[(Expression, Expression)]) -> R;
+    fn visit_logical_expression(&mut self, left: &Expression, operator: &Token, right: &Expression) -> R;
+    fn visit_set_expression(&mut self, object: &Expression, name: &Token, value: &Expression) -> R;
+    fn visit_set_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression, value: &Expression) -> R;
+    fn visit_super_expression(&mut self, id: usize, keyword: &Token, method: &Token) -> R;
+    fn visit_this_expression(&mut self, id: usize, keyword: &Token) -> R;
+    fn visit_unary_expression(&mut self, operator: &Token, right: &Expression) -> R;
+    fn visit_variable_expression(&mut self, id: usize, name: &Token) -> R;
 }
 
 impl Expression {
-    pub fn accept<T: ExpressionVisitor>(&self, visitor: T) {
+    pub fn accept<R, T: ExpressionVisitor<R>>(&self, visitor: &mut T) -> R {
         match self {
-            Binary => visitor.visit_binary_expression(self),
-            Grouping => visitor.visit_grouping_expression(self),
-            Unary => visitor.visit_unary_expression(self),
-            Literal => visitor.visit_literal_expression(self),
-            _ => println!(""),
+            Expression::Assign { id, name, value } => visitor.visit_assign_expression(*id, name, value),
+            Expression::Binary { left, right, operator } => visitor.visit_binary_expression(left, operator, right),
+            Expression::Call { callee, paren, arguments } => visitor.visit_call_expression(callee, paren, arguments),
+            Expression::CompoundAssign { id, name, operator, value, postfix } => visitor.visit_compound_assign_expression(*id, name, operator, value, *postfix),
+            Expression::CompoundSet { object, name, operator, value, postfix } => visitor.visit_compound_set_expression(object, name, operator, value, *postfix),
+            Expression::CompoundSetIndex { object, bracket, index, operator, value, postfix } => visitor.visit_compound_set_index_expression(object, bracket, index, operator, value, *postfix),
+            Expression::Conditional { condition, then_branch, else_branch } => visitor.visit_conditional_expression(condition, then_branch, else_branch),
+            Expression::Function { declaration } => visitor.visit_function_expression(declaration),
+            Expression::Get { object, name } => visitor.visit_get_expression(object, name),
+            Expression::Grouping { expression } => visitor.visit_grouping_expression(expression),
+            Expression::Index { object, bracket, index } => visitor.visit_index_expression(object, bracket, index),
+            Expression::Interpolation { parts } => visitor.visit_interpolation_expression(parts),
+            Expression::List { elements } => visitor.visit_list_expression(elements),
+            Expression::Literal { value } => visitor.visit_literal_expression(value),
+            Expression::Map { brace, entries } => visitor.visit_map_expression(brace, entries),
+            Expression::Logical { left, right, operator } => visitor.visit_logical_expression(left, operator, right),
+            Expression::Set { object, name, value } => visitor.visit_set_expression(object, name, value),
+            Expression::SetIndex { object, bracket, index, value } => visitor.visit_set_index_expression(object, bracket, index, value),
+            Expression::Super { id, keyword, method } => visitor.visit_super_expression(*id, keyword, method),
+            Expression::This { id, keyword } => visitor.visit_this_expression(*id, keyword),
+            Expression::Unary { operator, right } => visitor.visit_unary_expression(operator, right),
+            Expression::Variable { id, name } => visitor.visit_variable_expression(*id, name),
+        }
+    }
+
+    // Whether a call is in this expression, not counting function bodies which run when they are called
+    pub fn contains_call(&self) -> bool {
+        matches!(self, Expression::Call { .. }) || self.operands().into_iter().any(Expression::contains_call)
+    }
+
+    // The expressions evaluated to get this one's value, in the order they are evaluated
+    pub fn operands(&self) -> Vec<&Self> {
+        match self {
+            Expression::Assign { value, .. } | Expression::CompoundAssign { value, .. } => vec![value],
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => vec![left, right],
+            Expression::Call { callee, arguments, .. } => std::iter::once(&**callee).chain(arguments).collect(),
+            Expression::CompoundSet { object, value, .. } | Expression::Set { object, value, .. } => vec![object, value],
+            Expression::CompoundSetIndex { object, index, value, .. } | Expression::SetIndex { object, index, value, .. } => vec![object, index, value],
+            Expression::Conditional { condition, then_branch, else_branch } => vec![condition, then_branch, else_branch],
+            Expression::Get { object, .. } => vec![object],
+            Expression::Grouping { expression } => vec![expression],
+            Expression::Index { object, index, .. } => vec![object, index],
+            Expression::Interpolation { parts } => parts.iter().collect(),
+            Expression::List { elements } => elements.iter().collect(),
+            Expression::Map { entries, .. } => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+            Expression::Unary { right, .. } => vec![right],
+            Expression::Function { .. } | Expression::Literal { .. } | Expression::Super { .. } | Expression::This { .. } | Expression::Variable { .. } => Vec::new(),
+        }
+    }
+
+    // A copy with the operands replaced by the values they evaluated to, in the order of 'operands'
+    pub fn with_operands(&self, values: Vec<Object>) -> Self {
+        let mut expression: Self = self.clone();
+        let operands: Vec<&mut Self> = match &mut expression {
+            Expression::Assign { value, .. } | Expression::CompoundAssign { value, .. } => vec![value],
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => vec![left, right],
+            Expression::Call { callee, arguments, .. } => std::iter::once(&mut **callee).chain(arguments).collect(),
+            Expression::CompoundSet { object, value, .. } | Expression::Set { object, value, .. } => vec![object, value],
+            Expression::CompoundSetIndex { object, index, value, .. } | Expression::SetIndex { object, index, value, .. } => vec![object, index, value],
+            Expression::Conditional { condition, then_branch, else_branch } => vec![condition, then_branch, else_branch],
+            Expression::Get { object, .. } => vec![object],
+            Expression::Grouping { expression } => vec![expression],
+            Expression::Index { object, index, .. } => vec![object, index],
+            Expression::Interpolation { parts } => parts.iter_mut().collect(),
+            Expression::List { elements } => elements.iter_mut().collect(),
+            Expression::Map { entries, .. } => entries.iter_mut().flat_map(|(key, value)| [key, value]).collect(),
+            Expression::Unary { right, .. } => vec![right],
+            Expression::Function { .. } | Expression::Literal { .. } | Expression::Super { .. } | Expression::This { .. } | Expression::Variable { .. } => Vec::new(),
+        };
+        for (operand, value) in operands.into_iter().zip(values) {
+            *operand = Expression::Literal { value };
         }
+        expression
     }
 }
@@ -0,0 +1,188 @@
+use std::{cell::RefCell, collections::HashMap, fmt, mem, rc::Rc};
+
+use crate::{
+    channel::Channel,
+    class::Class,
+    environment::Environment,
+    function::{Function, NativeFunction},
+    generator::{Frame, Instruction, Slot},
+    interpreter::{Interpreter, RuntimeError},
+    object::Object,
+    token::Token,
+};
+
+// A function running alongside the others. It gives way at 'Fiber.yield()' or while a send or
+// receive on a channel waits, at any call depth: the functions it calls run as frames of its own,
+// compiled like generator bodies so each of them can stop at a call and carry on later.
+pub struct Fiber {
+    // Spawn order, which is also the order the scheduler runs fibers in
+    pub id: usize,
+    // The name of the function the fiber runs
+    pub name: String,
+    state: RefCell<State>,
+}
+
+enum State {
+    // 'wait' is what made the fiber give way, None before its first turn
+    Suspended { calls: Vec<Call>, wait: Option<Wait> },
+    Running,
+    Done,
+}
+
+// A call in progress in a fiber, the innermost is the last one
+pub struct Call {
+    pub function: Rc<Function>,
+    pub code: Rc<Vec<Instruction>>,
+    pub frame: Frame,
+}
+
+impl Call {
+    pub fn new(function: Rc<Function>, arguments: Vec<Object>) -> Self {
+        let mut environment: Environment = Environment::with_enclosing(function.closure.clone());
+        for (param, argument) in function.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+        let code: Rc<Vec<Instruction>> = function.declaration.fiber_code();
+        Self { function, code, frame: Frame::new(Rc::new(RefCell::new(environment))) }
+    }
+}
+
+// What a fiber that gave way waits for, set by the native function it called
+pub enum Wait {
+    // Its next turn, after 'Fiber.yield()'
+    Turn,
+    // A receiver to take the value it offered
+    Send(Rc<RefCell<Channel>>, usize),
+    // A value to receive
+    Receive(Rc<RefCell<Channel>>),
+}
+
+impl Wait {
+    // What the call that gave way returns, None while the fiber still has to wait
+    fn poll(&self) -> Option<Object> {
+        match self {
+            Wait::Turn => Some(Object::Null),
+            Wait::Send(channel, offer) => channel.borrow().is_taken(*offer).then_some(Object::Null),
+            Wait::Receive(channel) => channel.borrow_mut().take(),
+        }
+    }
+
+    fn on(&self) -> Option<&'static str> {
+        match self {
+            Wait::Turn => None,
+            Wait::Send(..) => Some("send"),
+            Wait::Receive(_) => Some("receive"),
+        }
+    }
+}
+
+// Where resuming a fiber left it
+pub enum Turn {
+    Yielded,
+    // 'progressed' is false when the fiber was resumed only to find it still has to wait
+    Waiting { on: &'static str, progressed: bool },
+    Finished,
+}
+
+impl Fiber {
+    pub fn new(id: usize, function: Rc<Function>) -> Self {
+        let name: String = function.declaration.name.lexeme.clone();
+        let calls: Vec<Call> = vec![Call::new(function, Vec::new())];
+        Self { id, name, state: RefCell::new(State::Suspended { calls, wait: None }) }
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(*self.state.borrow(), State::Done)
+    }
+
+    // "send" or "receive" while the fiber waits on a channel
+    pub fn waiting_on(&self) -> Option<&'static str> {
+        match &*self.state.borrow() {
+            State::Suspended { wait: Some(wait), .. } => wait.on(),
+            _ => None,
+        }
+    }
+
+    // Runs the fiber until it gives way or finishes
+    pub fn resume(&self, interpreter: &mut Interpreter, token: &Token) -> Result<Turn, RuntimeError> {
+        let state: State = mem::replace(&mut *self.state.borrow_mut(), State::Running);
+        let (mut calls, wait) = match state {
+            State::Suspended { calls, wait } => (calls, wait),
+            State::Running => return Err(RuntimeError::new(token, "Fiber is already running.")),
+            State::Done => {
+                *self.state.borrow_mut() = State::Done;
+                return Ok(Turn::Finished);
+            },
+        };
+
+        if let Some(wait) = wait {
+            match wait.poll() {
+                Some(value) => calls.last_mut().expect("A suspended fiber has a call").frame.stack.push(Slot::Value(value)),
+                None => {
+                    let on: &'static str = wait.on().expect("Only a channel keeps a fiber waiting");
+                    *self.state.borrow_mut() = State::Suspended { calls, wait: Some(wait) };
+                    return Ok(Turn::Waiting { on, progressed: false });
+                },
+            }
+        }
+
+        // An error ends the fiber like finishing its function does
+        let (state, turn) = match interpreter.run_fiber(&mut calls) {
+            Ok(Some(wait)) => {
+                let turn: Turn = match wait.on() {
+                    Some(on) => Turn::Waiting { on, progressed: true },
+                    None => Turn::Yielded,
+                };
+                (State::Suspended { calls, wait: Some(wait) }, Ok(turn))
+            },
+            Ok(None) => (State::Done, Ok(Turn::Finished)),
+            Err(error) => (State::Done, Err(error)),
+        };
+        *self.state.borrow_mut() = state;
+        turn
+    }
+
+    // The global 'Fiber', 'Fiber.spawn(function)' queues a fiber, 'Fiber.run()' runs the queue
+    // and 'Fiber.yield()' gives the next fiber a turn
+    pub fn class() -> Class {
+        let mut class: Class = Class::new("Fiber", None, HashMap::new());
+
+        class.native_methods.insert("spawn".to_string(), Rc::new(NativeFunction::new("spawn", 1, Rc::new(|interpreter, paren, mut arguments| {
+            let fiber: Rc<Fiber> = interpreter.spawn(paren, arguments.remove(0))?;
+            Ok(Object::Fiber(fiber))
+        }))));
+        class.native_methods.insert("run".to_string(), Rc::new(NativeFunction::new("run", 0, Rc::new(|interpreter, paren, _| {
+            interpreter.run_fibers(paren)?;
+            Ok(Object::Null)
+        }))));
+        class.native_methods.insert("yield".to_string(), Rc::new(NativeFunction::new("yield", 0, Rc::new(|interpreter, paren, _| {
+            interpreter.check_give_way(paren, "Can only yield from inside a fiber.")?;
+            interpreter.give_way(Wait::Turn);
+            Ok(Object::Null)
+        }))));
+        class
+    }
+
+    pub fn method(fiber: &Rc<Fiber>, name: &Token) -> Option<NativeFunction> {
+        let fiber: Rc<Fiber> = fiber.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            // Runs the fiber by itself until it gives way or finishes
+            "resume" => NativeFunction::new("resume", 0, Rc::new(move |interpreter, paren, _| {
+                fiber.resume(interpreter, paren)?;
+                Ok(Object::Null)
+            })),
+            "done" => NativeFunction::new("done", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Boolean(fiber.is_done()))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+impl fmt::Debug for Fiber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fiber {}>", self.name)
+    }
+}
@@ -0,0 +1,61 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    class::Instance,
+    environment::Environment,
+    interpreter::{Interpreter, RuntimeError},
+    object::Object,
+    statement::FunctionDeclaration,
+    token::Token,
+};
+
+pub struct Function {
+    pub declaration: Rc<FunctionDeclaration>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+}
+
+impl Function {
+    pub fn new(declaration: Rc<FunctionDeclaration>, closure: Rc<RefCell<Environment>>, is_initializer: bool) -> Self {
+        Self { declaration, closure, is_initializer }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    // A copy of the method whose closure has 'this' bound to 'instance'
+    pub fn bind(&self, instance: Rc<RefCell<Instance>>) -> Function {
+        let mut environment: Environment = Environment::with_enclosing(self.closure.clone());
+        environment.define("this", Object::Instance(instance));
+        Function::new(self.declaration.clone(), Rc::new(RefCell::new(environment)), self.is_initializer)
+    }
+}
+
+// The closure can reach the function itself, so only the name is printed
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+pub type NativeBody = dyn Fn(&mut Interpreter, &Token, Vec<Object>) -> Result<Object, RuntimeError>;
+
+// A function implemented in Rust, 'paren' is passed along so errors point at the call site
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: usize,
+    pub body: Rc<NativeBody>,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: usize, body: Rc<NativeBody>) -> Self {
+        Self { name: name.to_string(), arity, body }
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
@@ -0,0 +1,548 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    environment::Environment,
+    expression::Expression,
+    function::NativeFunction,
+    interpreter::{Iteration, RuntimeError},
+    object::Object,
+    pattern::Pattern,
+    statement::{FunctionDeclaration, Statement},
+    token::Token,
+    token_type::TokenType,
+};
+
+// A generator body compiled so it can stop at a 'yield' and carry on later. Statements without
+// a 'yield' in them are kept whole and run by the tree-walking interpreter, only the control
+// flow around the yields is flattened into jumps. A function running in a fiber is compiled the
+// same way around its calls, and the expressions with calls in them are flattened too, so the
+// fiber can give way inside any call and carry on later.
+#[derive(Debug)]
+pub enum Instruction {
+    // 'exits' is where a break, continue or return out of the statement goes
+    Execute { statement: Box<Statement>, exits: Option<Exits> },
+    // Pushes the value of an expression without calls in it
+    Push { expression: Expression },
+    // Pops the values of the expression's operands and pushes the value it has with them
+    Apply { expression: Expression },
+    // Pops the arguments and the callee, then pushes what the call returns
+    Call { paren: Token, arguments: usize },
+    // Pops the value to yield
+    Yield,
+    // Pops the condition
+    JumpUnless { target: usize },
+    // For 'and' and 'or', jumps keeping the value on top when its truthiness is 'when', or pops it
+    JumpKeeping { when: bool, target: usize },
+    Jump { target: usize },
+    BeginScope,
+    EndScope,
+    // Pops the iterable and starts a for-in loop over it
+    IterStart { keyword: Token },
+    // Binds the next value in a new scope, or ends the loop and jumps to 'exit'
+    IterNext { keyword: Token, name: Token, exit: usize },
+    // Errors until the matching PopHandler go to 'target'
+    PushHandler { target: usize },
+    PopHandler,
+    // At a handler, binds the caught error in a new scope
+    Catch { name: Token },
+    // At a handler, keeps the caught error for Rethrow while a finally block runs
+    SaveError,
+    Rethrow,
+    // Tries an arm in a new scope above the value being matched, jumping to 'next' when the pattern fails
+    MatchArm { pattern: Pattern, next: usize },
+    PopValue,
+    // Pops the variable's initial value
+    Define { name: Token },
+    // Pops the value to print
+    Print,
+    // Pops the value to throw
+    Throw { keyword: Token },
+    // Pops the value to return, the code leaving the body follows
+    Return,
+    // Drops everything above 'height' on the stack, leaving scopes, loops and handlers on the way
+    Unwind { height: usize },
+    Finish,
+}
+
+// Exit stubs run the finally blocks being left before jumping
+#[derive(Debug, Clone, Copy)]
+pub struct Exits {
+    pub on_break: Option<usize>,
+    pub on_continue: Option<usize>,
+    pub on_return: usize,
+}
+
+// What a suspended generator keeps on its stack, pushed and popped as blocks nest
+pub enum Slot {
+    // The environment to go back to when the scope ends
+    Scope(Rc<RefCell<Environment>>),
+    Iteration(Iteration),
+    Handler(usize),
+    Error(RuntimeError),
+    // The value being matched, or an operand waiting for the rest of its expression
+    Value(Object),
+}
+
+// Where a generator is in its body
+pub struct Frame {
+    pub ip: usize,
+    pub environment: Rc<RefCell<Environment>>,
+    pub stack: Vec<Slot>,
+    // The error a handler is about to bind or save
+    pub caught: Option<RuntimeError>,
+    // What a return is returning while the finally blocks it leaves run
+    pub returned: Option<Object>,
+}
+
+impl Frame {
+    // At the start of a body, 'environment' holds the arguments
+    pub fn new(environment: Rc<RefCell<Environment>>) -> Self {
+        Self { ip: 0, environment, stack: Vec::new(), caught: None, returned: None }
+    }
+}
+
+pub enum State {
+    Suspended(Frame),
+    Running,
+    Done,
+}
+
+pub struct Generator {
+    pub declaration: Rc<FunctionDeclaration>,
+    pub state: State,
+}
+
+impl Generator {
+    // A generator waiting at the start of its body, 'environment' holds the arguments
+    pub fn new(declaration: Rc<FunctionDeclaration>, environment: Rc<RefCell<Environment>>) -> Self {
+        Self { declaration, state: State::Suspended(Frame::new(environment)) }
+    }
+
+    pub fn method(generator: &Rc<RefCell<Generator>>, name: &Token) -> Option<NativeFunction> {
+        let generator: Rc<RefCell<Generator>> = generator.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            // nil once the body has finished, like any other iterator
+            "next" => NativeFunction::new("next", 0, Rc::new(move |interpreter, paren, _| {
+                Ok(interpreter.resume(&generator, paren)?.unwrap_or(Object::Null))
+            })),
+            "iter" => NativeFunction::new("iter", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Generator(generator.clone()))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<generator {}>", self.declaration.name.lexeme)
+    }
+}
+
+pub fn compile(body: &[Statement]) -> Vec<Instruction> {
+    Compiler::default().body(body)
+}
+
+// A function body for running in a fiber, which gives way inside calls instead of at yields
+pub fn compile_for_fiber(body: &[Statement]) -> Vec<Instruction> {
+    Compiler { for_fiber: true, ..Compiler::default() }.body(body)
+}
+
+#[derive(Clone, Copy)]
+enum Exit {
+    Break,
+    Continue,
+    Return,
+}
+
+struct Loop {
+    break_height: usize,
+    continue_height: usize,
+    // Jumps to patch once the end and the continue target are known
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+    // How many finally regions enclose the loop
+    finallies: usize,
+}
+
+// A try with a finally block, exits from inside it run the block on their way out
+struct Finally {
+    body: Vec<Statement>,
+    height: usize,
+    // How many loops enclose the try
+    loops: usize,
+}
+
+#[derive(Default)]
+struct Compiler {
+    // Compiling around calls for a fiber instead of around yields
+    for_fiber: bool,
+    code: Vec<Instruction>,
+    // The generator's stack height at the next instruction, blocks nest so it is known here
+    height: usize,
+    loops: Vec<Loop>,
+    finallies: Vec<Finally>,
+}
+
+impl Compiler {
+    fn body(mut self, body: &[Statement]) -> Vec<Instruction> {
+        self.statements(body);
+        self.code.push(Instruction::Finish);
+        self.code
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        let suspends: bool = if self.for_fiber { statement.contains_call() } else { statement.contains_yield() };
+        if !suspends {
+            self.execute(statement);
+            return;
+        }
+
+        match statement {
+            Statement::Block { statements } => self.block(statements),
+            Statement::If { condition, then_branch, else_branch } => {
+                let jump_else: usize = self.jump_unless(condition);
+                self.statement(then_branch);
+
+                match else_branch {
+                    Some(else_branch) => {
+                        let jump_end: usize = self.emit(Instruction::Jump { target: 0 });
+                        self.patch(jump_else);
+                        self.statement(else_branch);
+                        self.patch(jump_end);
+                    },
+                    None => self.patch(jump_else),
+                }
+            },
+            Statement::While { condition, body, increment } => {
+                let start: usize = self.code.len();
+                let exit: usize = self.jump_unless(condition);
+
+                self.begin_loop(self.height, self.height);
+                self.statement(body);
+                let continue_target: usize = self.code.len();
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                    self.emit(Instruction::PopValue);
+                    self.height -= 1;
+                }
+                self.emit(Instruction::Jump { target: start });
+
+                self.patch(exit);
+                self.end_loop(continue_target);
+            },
+            Statement::ForIn { name, keyword, iterable, body } => {
+                // The iteration takes the iterable's place on the stack
+                let break_height: usize = self.height;
+                self.expression(iterable);
+                self.emit(Instruction::IterStart { keyword: keyword.clone() });
+
+                // Each value gets its own scope, like the tree-walking loop
+                let next: usize = self.emit(Instruction::IterNext { keyword: keyword.clone(), name: name.clone(), exit: 0 });
+                self.height += 1;
+                self.begin_loop(break_height, break_height + 1);
+                self.statement(body);
+                self.emit(Instruction::EndScope);
+                self.height -= 1;
+                self.emit(Instruction::Jump { target: next });
+
+                // IterNext drops the iteration when it runs out
+                self.height -= 1;
+                self.patch(next);
+                self.end_loop(next);
+            },
+            Statement::Match { value, arms } => {
+                // The value stays on the stack while the arms are tried
+                self.expression(value);
+
+                let mut ends: Vec<usize> = Vec::new();
+                for arm in arms {
+                    let attempt: usize = self.emit(Instruction::MatchArm { pattern: arm.pattern.clone(), next: 0 });
+                    self.height += 1;
+                    let failed_guard: Option<usize> = arm.guard.as_ref().map(|guard| self.jump_unless(guard));
+                    self.statement(&arm.body);
+                    self.emit(Instruction::EndScope);
+                    ends.push(self.emit(Instruction::Jump { target: 0 }));
+
+                    // A failed guard leaves the arm's scope on the way to the next arm
+                    if let Some(failed_guard) = failed_guard {
+                        self.patch(failed_guard);
+                        self.emit(Instruction::EndScope);
+                    }
+                    self.height -= 1;
+                    self.patch(attempt);
+                }
+                for end in ends {
+                    self.patch(end);
+                }
+
+                self.emit(Instruction::PopValue);
+                self.height -= 1;
+            },
+            Statement::Try { body, catch, finally } => {
+                let finally_handler: Option<usize> = finally.as_ref().map(|finally| {
+                    let handler: usize = self.emit(Instruction::PushHandler { target: 0 });
+                    self.finallies.push(Finally { body: finally.clone(), height: self.height, loops: self.loops.len() });
+                    self.height += 1;
+                    handler
+                });
+
+                match catch {
+                    Some(catch) => {
+                        let handler: usize = self.emit(Instruction::PushHandler { target: 0 });
+                        self.height += 1;
+                        self.block(body);
+                        self.emit(Instruction::PopHandler);
+                        self.height -= 1;
+                        let skip: usize = self.emit(Instruction::Jump { target: 0 });
+
+                        self.patch(handler);
+                        self.emit(Instruction::Catch { name: catch.name.clone() });
+                        self.height += 1;
+                        self.statements(&catch.body);
+                        self.emit(Instruction::EndScope);
+                        self.height -= 1;
+                        self.patch(skip);
+                    },
+                    None => self.block(body),
+                }
+
+                if let (Some(finally), Some(handler)) = (finally, finally_handler) {
+                    self.finallies.pop();
+                    self.emit(Instruction::PopHandler);
+                    self.height -= 1;
+                    self.block(finally);
+                    let skip: usize = self.emit(Instruction::Jump { target: 0 });
+
+                    // On an error the block runs with the error kept aside, then the error carries on
+                    self.patch(handler);
+                    self.emit(Instruction::SaveError);
+                    self.height += 1;
+                    self.block(finally);
+                    self.emit(Instruction::Rethrow);
+                    self.height -= 1;
+                    self.patch(skip);
+                }
+            },
+            Statement::Yield { value, .. } => {
+                self.value(value.as_ref());
+                self.emit(Instruction::Yield);
+                self.height -= 1;
+            },
+            // The rest only come here with a call in them, in a fiber
+            Statement::Expression { expression } => {
+                self.expression(expression);
+                self.emit(Instruction::PopValue);
+                self.height -= 1;
+            },
+            Statement::Print { expression } => {
+                self.expression(expression);
+                self.emit(Instruction::Print);
+                self.height -= 1;
+            },
+            Statement::Var { name, initializer, .. } => {
+                self.value(initializer.as_ref());
+                self.emit(Instruction::Define { name: name.clone() });
+                self.height -= 1;
+            },
+            Statement::Throw { keyword, value } => {
+                self.expression(value);
+                self.emit(Instruction::Throw { keyword: keyword.clone() });
+                self.height -= 1;
+            },
+            Statement::Return { value, .. } => {
+                self.value(value.as_ref());
+                self.emit(Instruction::Return);
+                self.height -= 1;
+                self.exit(Exit::Return);
+            },
+            _ => unreachable!("Only statements with nested statements or expressions can contain a yield or a call"),
+        }
+    }
+
+    // Code that leaves the expression's value on the stack. Outside of a fiber, or without a call
+    // in it, the tree-walking interpreter evaluates it in one go.
+    fn expression(&mut self, expression: &Expression) {
+        if !(self.for_fiber && expression.contains_call()) {
+            self.emit(Instruction::Push { expression: expression.clone() });
+            self.height += 1;
+            return;
+        }
+
+        match expression {
+            Expression::Call { paren, arguments, .. } => {
+                for operand in expression.operands() {
+                    self.expression(operand);
+                }
+                self.emit(Instruction::Call { paren: paren.clone(), arguments: arguments.len() });
+                self.height -= arguments.len();
+            },
+            Expression::Grouping { expression } => self.expression(expression),
+            Expression::Logical { left, right, operator } => {
+                self.expression(left);
+                let jump_end: usize = self.emit(Instruction::JumpKeeping { when: operator.token_type == TokenType::Or, target: 0 });
+                self.height -= 1;
+                self.expression(right);
+                self.patch(jump_end);
+            },
+            Expression::Conditional { condition, then_branch, else_branch } => {
+                let jump_else: usize = self.jump_unless(condition);
+                self.expression(then_branch);
+                let jump_end: usize = self.emit(Instruction::Jump { target: 0 });
+                self.height -= 1;
+                self.patch(jump_else);
+                self.expression(else_branch);
+                self.patch(jump_end);
+            },
+            // The operands are worked out here, the operation itself is left to the tree-walking interpreter
+            _ => {
+                let operands: Vec<&Expression> = expression.operands();
+                for operand in &operands {
+                    self.expression(operand);
+                }
+                self.emit(Instruction::Apply { expression: expression.clone() });
+                self.height -= operands.len() - 1;
+            },
+        }
+    }
+
+    // A missing value is nil
+    fn value(&mut self, value: Option<&Expression>) {
+        match value {
+            Some(value) => self.expression(value),
+            None => {
+                self.emit(Instruction::Push { expression: Expression::Literal { value: Object::Null } });
+                self.height += 1;
+            },
+        }
+    }
+
+    // Returns the jump to patch with where a false condition goes
+    fn jump_unless(&mut self, condition: &Expression) -> usize {
+        self.expression(condition);
+        self.height -= 1;
+        self.emit(Instruction::JumpUnless { target: 0 })
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        self.emit(Instruction::BeginScope);
+        self.height += 1;
+        self.statements(statements);
+        self.emit(Instruction::EndScope);
+        self.height -= 1;
+    }
+
+    // A statement without a yield, followed by the stubs its break, continue or return jump to
+    fn execute(&mut self, statement: &Statement) {
+        let index: usize = self.emit(Instruction::Execute { statement: Box::new(statement.clone()), exits: None });
+        if !statement.can_exit() {
+            return;
+        }
+
+        let skip: usize = self.emit(Instruction::Jump { target: 0 });
+        let in_loop: bool = !self.loops.is_empty();
+        let exits: Exits = Exits {
+            on_break: in_loop.then(|| self.exit(Exit::Break)),
+            on_continue: in_loop.then(|| self.exit(Exit::Continue)),
+            on_return: self.exit(Exit::Return),
+        };
+        self.patch(skip);
+
+        if let Instruction::Execute { exits: slot, .. } = &mut self.code[index] {
+            *slot = Some(exits);
+        }
+    }
+
+    // Code that leaves the innermost loop, or the whole body for a return, running the
+    // finally blocks in between from the innermost out. Returns where it starts.
+    fn exit(&mut self, exit: Exit) -> usize {
+        let start: usize = self.code.len();
+        let first: usize = match exit {
+            Exit::Break | Exit::Continue => self.loops.last().map_or(0, |enclosing| enclosing.finallies),
+            Exit::Return => 0,
+        };
+
+        for index in (first..self.finallies.len()).rev() {
+            let height: usize = self.finallies[index].height;
+            self.emit(Instruction::Unwind { height });
+
+            // The block runs outside its own try, so its own exits only see what encloses the try
+            let inner_finallies: Vec<Finally> = self.finallies.split_off(index);
+            let inner_loops: Vec<Loop> = self.loops.split_off(inner_finallies[0].loops);
+            let current_height: usize = std::mem::replace(&mut self.height, height);
+
+            let body: Vec<Statement> = inner_finallies[0].body.clone();
+            self.block(&body);
+
+            self.height = current_height;
+            self.loops.extend(inner_loops);
+            self.finallies.extend(inner_finallies);
+        }
+
+        match exit {
+            Exit::Break | Exit::Continue => {
+                let Some(enclosing) = self.loops.last() else {
+                    unreachable!("The resolver rejects 'break' and 'continue' outside of a loop");
+                };
+                let height: usize = if let Exit::Break = exit { enclosing.break_height } else { enclosing.continue_height };
+                self.emit(Instruction::Unwind { height });
+
+                let jump: usize = self.emit(Instruction::Jump { target: 0 });
+                let enclosing: &mut Loop = self.loops.last_mut().unwrap();
+                match exit {
+                    Exit::Break => enclosing.breaks.push(jump),
+                    _ => enclosing.continues.push(jump),
+                }
+            },
+            Exit::Return => {
+                self.emit(Instruction::Finish);
+            },
+        }
+        start
+    }
+
+    fn begin_loop(&mut self, break_height: usize, continue_height: usize) {
+        self.loops.push(Loop { break_height, continue_height, breaks: Vec::new(), continues: Vec::new(), finallies: self.finallies.len() });
+    }
+
+    // Breaks go to the next instruction
+    fn end_loop(&mut self, continue_target: usize) {
+        let finished: Loop = self.loops.pop().unwrap();
+        for jump in finished.breaks {
+            self.patch(jump);
+        }
+        for jump in finished.continues {
+            self.patch_to(jump, continue_target);
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    // Points the jump at 'index' to the next instruction
+    fn patch(&mut self, index: usize) {
+        self.patch_to(index, self.code.len());
+    }
+
+    fn patch_to(&mut self, index: usize, destination: usize) {
+        match &mut self.code[index] {
+            Instruction::JumpUnless { target }
+            | Instruction::JumpKeeping { target, .. }
+            | Instruction::Jump { target }
+            | Instruction::PushHandler { target } => *target = destination,
+            Instruction::IterNext { exit, .. } => *exit = destination,
+            Instruction::MatchArm { next, .. } => *next = destination,
+            instruction => unreachable!("Can't patch {:?}", instruction),
+        }
+    }
+}
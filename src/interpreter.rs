@@ -0,0 +1,2782 @@
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, io::{self, Write}, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::{
+    bigint::BigInt,
+    channel::Channel,
+    class::{Class, Instance},
+    environment::Environment,
+    expression::{Expression, ExpressionVisitor},
+    fiber::{Call, Fiber, Turn, Wait},
+    function::{Function, NativeFunction},
+    generator::{Frame, Generator, Instruction, Slot, State},
+    list::List,
+    map::Map,
+    object::Object,
+    pattern::Pattern,
+    range::Range,
+    rational::Rational,
+    set::Set,
+    statement::{CatchClause, FunctionDeclaration, MatchArm, Method, MethodKind, Statement, StatementVisitor},
+    token::Token,
+    token_type::TokenType,
+};
+
+// How deep calls can nest before a runaway recursion is reported as a stack overflow
+pub const MAX_CALL_DEPTH: usize = 4_000;
+
+// The stack a program needs to reach MAX_CALL_DEPTH, the interpreter has to run on a thread this big
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    // Boxed so the Err side of every result stays small
+    pub token: Box<Token>,
+    pub message: String,
+    // What 'throw' threw, errors raised by the interpreter itself have none
+    pub value: Option<Object>,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: &str) -> Self {
+        Self { token: Box::new(token.clone()), message: message.to_string(), value: None }
+    }
+
+    // An uncaught error object reports its 'message' field, anything else is printed as is
+    pub fn thrown(token: &Token, value: Object) -> Self {
+        let message: String = match &value {
+            Object::Instance(instance) => match instance.borrow().fields.get("message") {
+                Some(Object::String(message)) => message.clone(),
+                _ => value.to_string(),
+            },
+            _ => value.to_string(),
+        };
+        Self { token: Box::new(token.clone()), message, value: Some(value) }
+    }
+}
+
+// Why a statement stopped before running to its end
+#[derive(Debug)]
+pub enum Interrupt {
+    Error(RuntimeError),
+    Return(Object),
+    Break,
+    Continue,
+}
+
+impl From<RuntimeError> for Interrupt {
+    fn from(error: RuntimeError) -> Self {
+        Interrupt::Error(error)
+    }
+}
+
+// Where a for-in loop is in its iterable
+pub enum Iteration {
+    // Lists are read by index, so elements pushed during the loop are visited too
+    List(Rc<RefCell<List>>, usize),
+    // A snapshot of map keys, set elements or the characters of a string
+    Values(std::vec::IntoIter<Object>),
+    // The next value and the first value past the end
+    Range(i64, i64),
+    // The object returned by 'iter()', its 'next()' returns nil when it is done
+    Iterator(Object),
+    // Generators say when they are done, so they can yield nil
+    Generator(Rc<RefCell<Generator>>),
+}
+
+// What a generator instruction leaves the generator to do next
+enum Step {
+    Next,
+    Yield(Object),
+    Finish,
+}
+
+pub struct Interpreter {
+    pub globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // Scope distance of each local variable expression, keyed by expression id
+    locals: HashMap<usize, usize>,
+    // The class of the objects that catch clauses receive for built-in runtime errors
+    error_class: Rc<Class>,
+    output: Rc<RefCell<dyn Write>>,
+    // Spawned fibers that haven't finished, in the order they take turns
+    fibers: Vec<Rc<Fiber>>,
+    next_fiber_id: usize,
+    // Set while a fiber runs
+    in_fiber: bool,
+    // Set while a native function called by the fiber's own code runs, only those can make it give way
+    can_give_way: bool,
+    // What the fiber waits for, set by the native function that made it give way
+    giving_way: Option<Wait>,
+    // Calls in progress, checked against MAX_CALL_DEPTH
+    depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_output(Rc::new(RefCell::new(io::stdout())))
+    }
+
+    // Everything the program prints goes to 'output' instead of stdout
+    pub fn with_output(output: Rc<RefCell<dyn Write>>) -> Self {
+        let globals: Rc<RefCell<Environment>> = Rc::new(RefCell::new(Environment::new()));
+
+        globals.borrow_mut().define("clock", Object::NativeFunction(Rc::new(NativeFunction::new("clock", 0, Rc::new(|_, _, _| {
+            let now: f64 = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
+            Ok(Object::Number(now))
+        })))));
+
+        let error_class: Rc<Class> = Rc::new(Class::new("Error", None, HashMap::new()));
+        globals.borrow_mut().define("Error", Object::Class(error_class.clone()));
+
+        globals.borrow_mut().define("Set", Object::NativeFunction(Rc::new(NativeFunction::new("Set", 0, Rc::new(|_, _, _| {
+            Ok(Object::Set(Rc::new(RefCell::new(Set::new()))))
+        })))));
+
+        globals.borrow_mut().define("rational", Object::NativeFunction(Rc::new(NativeFunction::new("rational", 2, Rc::new(|_, paren, arguments| {
+            let (Some(numerator), Some(denominator)) = (arguments[0].as_big_int(), arguments[1].as_big_int()) else {
+                return Err(RuntimeError::new(paren, "Numerator and denominator must be integers."));
+            };
+            match Rational::new(numerator, denominator) {
+                Some(rational) => Ok(Object::Rational(rational)),
+                None => Err(RuntimeError::new(paren, "Division by zero.")),
+            }
+        })))));
+
+        globals.borrow_mut().define("Fiber", Object::Class(Rc::new(Fiber::class())));
+        globals.borrow_mut().define("Channel", Object::NativeFunction(Rc::new(NativeFunction::new("Channel", 0, Rc::new(|_, _, _| {
+            Ok(Object::Channel(Rc::new(RefCell::new(Channel::new()))))
+        })))));
+
+        Self {
+            environment: globals.clone(),
+            globals,
+            locals: HashMap::new(),
+            error_class,
+            output,
+            fibers: Vec::new(),
+            next_fiber_id: 1,
+            in_fiber: false,
+            can_give_way: false,
+            giving_way: None,
+            depth: 0,
+        }
+    }
+
+    pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {},
+                Err(Interrupt::Error(error)) => return Err(error),
+                // A top level return ends the program
+                Err(Interrupt::Return(_)) => return Ok(()),
+                // The resolver rejects 'break' and 'continue' outside of a loop
+                Err(Interrupt::Break | Interrupt::Continue) => {},
+            }
+        }
+        Ok(())
+    }
+
+    // Takes the scope distances found by the resolver, kept across REPL lines
+    pub fn resolve(&mut self, locals: HashMap<usize, usize>) {
+        self.locals.extend(locals);
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<(), Interrupt> {
+        statement.accept(self)
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<Object, RuntimeError> {
+        expression.accept(self)
+    }
+
+    pub fn execute_block(&mut self, statements: &[Statement], environment: Rc<RefCell<Environment>>) -> Result<(), Interrupt> {
+        let previous: Rc<RefCell<Environment>> = std::mem::replace(&mut self.environment, environment);
+        let result: Result<(), Interrupt> = statements.iter().try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+
+    // Calls anything callable, natives use this to call back into Lox code
+    pub fn call(&mut self, callee: Object, paren: &Token, arguments: Vec<Object>) -> Result<Object, RuntimeError> {
+        match callee {
+            Object::Function(function) => {
+                Self::check_arity(paren, function.arity(), arguments.len())?;
+                self.call_function(&function, paren, arguments)
+            },
+            Object::NativeFunction(function) => {
+                Self::check_arity(paren, function.arity, arguments.len())?;
+                let body = function.body.clone();
+                self.nested(paren, |interpreter| body(interpreter, paren, arguments))
+            },
+            Object::Class(class) => {
+                Self::check_arity(paren, class.arity(), arguments.len())?;
+                let instance: Rc<RefCell<Instance>> = Rc::new(RefCell::new(Instance::new(class.clone())));
+                if let Some(initializer) = class.find_method("init") {
+                    self.call_function(&initializer.bind(instance.clone()), paren, arguments)?;
+                }
+                Ok(Object::Instance(instance))
+            },
+            _ => Err(RuntimeError::new(paren, "Can only call functions and classes.")),
+        }
+    }
+
+    // Runs 'call' one call deeper, a runaway recursion stops with an error that can be caught
+    // before it overflows the real stack. A fiber can't give way from inside it, there is no
+    // frame of the fiber's own to carry on from.
+    fn nested<T>(&mut self, token: &Token, call: impl FnOnce(&mut Self) -> Result<T, RuntimeError>) -> Result<T, RuntimeError> {
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(token, "Stack overflow."));
+        }
+
+        self.depth += 1;
+        let can_give_way: bool = std::mem::replace(&mut self.can_give_way, false);
+        let result: Result<T, RuntimeError> = call(self);
+        self.can_give_way = can_give_way;
+        self.depth -= 1;
+        result
+    }
+
+    // 'token' is where the call happens, for the error when calls nest too deep
+    fn call_function(&mut self, function: &Function, token: &Token, arguments: Vec<Object>) -> Result<Object, RuntimeError> {
+        self.nested(token, |interpreter| interpreter.run_function(function, arguments))
+    }
+
+    fn run_function(&mut self, function: &Function, arguments: Vec<Object>) -> Result<Object, RuntimeError> {
+        let mut environment: Environment = Environment::with_enclosing(function.closure.clone());
+        for (param, argument) in function.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        // A generator's body waits for the first 'next()'
+        if function.declaration.generator.is_some() {
+            let generator: Generator = Generator::new(function.declaration.clone(), Rc::new(RefCell::new(environment)));
+            return Ok(Object::Generator(Rc::new(RefCell::new(generator))));
+        }
+
+        let value: Object = match self.execute_block(&function.declaration.body, Rc::new(RefCell::new(environment))) {
+            Ok(()) | Err(Interrupt::Break | Interrupt::Continue) => Object::Null,
+            Err(Interrupt::Return(value)) => value,
+            Err(Interrupt::Error(error)) => return Err(error),
+        };
+
+        // An initializer always hands back the instance, even on an early return
+        if function.is_initializer {
+            return Ok(function.closure.borrow().get_here("this").unwrap_or(Object::Null));
+        }
+        Ok(value)
+    }
+
+    fn check_arity(paren: &Token, expected: usize, got: usize) -> Result<(), RuntimeError> {
+        if expected != got {
+            return Err(RuntimeError::new(paren, &format!("Expected {} arguments but got {}.", expected, got)));
+        }
+        Ok(())
+    }
+
+    pub fn iteration(&mut self, keyword: &Token, iterable: Object) -> Result<Iteration, RuntimeError> {
+        match iterable {
+            Object::List(list) => Ok(Iteration::List(list, 0)),
+            Object::Range(range) => Ok(Iteration::Range(range.start, range.stop())),
+            Object::Map(map) => {
+                let keys: Vec<Object> = map.borrow().entries().iter().map(|(key, _)| key.clone()).collect();
+                Ok(Iteration::Values(keys.into_iter()))
+            },
+            Object::Set(set) => Ok(Iteration::Values(set.borrow().elements().to_vec().into_iter())),
+            Object::String(string) => {
+                let characters: Vec<Object> = string.chars().map(|character| Object::String(character.to_string())).collect();
+                Ok(Iteration::Values(characters.into_iter()))
+            },
+            Object::Generator(generator) => Ok(Iteration::Generator(generator)),
+            Object::Instance(_) => {
+                let iter: Token = Token::new(TokenType::Identifier, "iter".to_string(), Object::Null, keyword.line);
+                let method: Object = self.get_property(iterable, &iter)?;
+                match self.call(method, keyword, Vec::new())? {
+                    Object::Generator(generator) => Ok(Iteration::Generator(generator)),
+                    iterator => Ok(Iteration::Iterator(iterator)),
+                }
+            },
+            _ => Err(RuntimeError::new(keyword, "Can only iterate over lists, maps, sets, strings, ranges and iterators.")),
+        }
+    }
+
+    pub fn next_value(&mut self, keyword: &Token, iteration: &mut Iteration) -> Result<Option<Object>, RuntimeError> {
+        match iteration {
+            Iteration::List(list, index) => {
+                let value: Option<Object> = list.borrow().elements.get(*index).cloned();
+                *index += 1;
+                Ok(value)
+            },
+            Iteration::Range(next, stop) => {
+                if *next >= *stop {
+                    return Ok(None);
+                }
+                *next += 1;
+                Ok(Some(Object::Integer(*next - 1)))
+            },
+            Iteration::Values(values) => Ok(values.next()),
+            Iteration::Iterator(iterator) => {
+                let next: Token = Token::new(TokenType::Identifier, "next".to_string(), Object::Null, keyword.line);
+                let method: Object = self.get_property(iterator.clone(), &next)?;
+                match self.call(method, keyword, Vec::new())? {
+                    Object::Null => Ok(None),
+                    value => Ok(Some(value)),
+                }
+            },
+            Iteration::Generator(generator) => self.resume(generator, keyword),
+        }
+    }
+
+    // Runs a generator up to its next 'yield', None once its body has finished
+    pub fn resume(&mut self, generator: &Rc<RefCell<Generator>>, token: &Token) -> Result<Option<Object>, RuntimeError> {
+        let (code, mut frame) = {
+            let mut generator = generator.borrow_mut();
+            let frame: Frame = match std::mem::replace(&mut generator.state, State::Running) {
+                State::Suspended(frame) => frame,
+                State::Running => return Err(RuntimeError::new(token, "Generator is already running.")),
+                State::Done => {
+                    generator.state = State::Done;
+                    return Ok(None);
+                },
+            };
+            let code: Rc<Vec<Instruction>> = generator.declaration.generator.clone().expect("Generators are made from generator functions");
+            (code, frame)
+        };
+
+        let previous: Rc<RefCell<Environment>> = std::mem::replace(&mut self.environment, frame.environment.clone());
+        let result: Result<Option<Object>, RuntimeError> = self.run_generator(&code, &mut frame);
+        frame.environment = std::mem::replace(&mut self.environment, previous);
+
+        // An error ends the generator like finishing its body does
+        generator.borrow_mut().state = match result {
+            Ok(Some(_)) => State::Suspended(frame),
+            _ => State::Done,
+        };
+        result
+    }
+
+    fn run_generator(&mut self, code: &[Instruction], frame: &mut Frame) -> Result<Option<Object>, RuntimeError> {
+        loop {
+            let instruction: &Instruction = &code[frame.ip];
+            frame.ip += 1;
+
+            match self.step(instruction, frame) {
+                Ok(Step::Next) => {},
+                Ok(Step::Yield(value)) => return Ok(Some(value)),
+                Ok(Step::Finish) => {
+                    self.unwind(frame, 0);
+                    return Ok(None);
+                },
+                // The innermost try around the error in the generator body gets it
+                Err(error) => match self.unwind_to_handler(frame) {
+                    Some(target) => {
+                        frame.caught = Some(error);
+                        frame.ip = target;
+                    },
+                    None => return Err(error),
+                },
+            }
+        }
+    }
+
+    // Queues a fiber running 'function', it starts on the next 'Fiber.run()' or 'resume()'
+    pub fn spawn(&mut self, paren: &Token, function: Object) -> Result<Rc<Fiber>, RuntimeError> {
+        let Object::Function(function) = function else {
+            return Err(RuntimeError::new(paren, "Can only spawn a fiber from a function."));
+        };
+        if function.arity() != 0 {
+            return Err(RuntimeError::new(paren, "A fiber's function can't take arguments."));
+        }
+        // Calling it would only make a generator, 'yield' doesn't give way in a fiber
+        if function.declaration.generator.is_some() {
+            return Err(RuntimeError::new(paren, "Can't spawn a fiber from a generator, use 'Fiber.yield()' to give way."));
+        }
+
+        let fiber: Rc<Fiber> = Rc::new(Fiber::new(self.next_fiber_id, function));
+        self.next_fiber_id += 1;
+        self.fibers.push(fiber.clone());
+        Ok(fiber)
+    }
+
+    // Round-robin over the spawned fibers until all of them finish. Fibers spawned during a round
+    // get their first turn in the same round. A round where no fiber gets anywhere is a deadlock.
+    pub fn run_fibers(&mut self, paren: &Token) -> Result<(), RuntimeError> {
+        if self.in_fiber {
+            return Err(RuntimeError::new(paren, "Can't run fibers from inside a fiber."));
+        }
+
+        let result: Result<(), RuntimeError> = self.schedule(paren);
+        self.fibers.retain(|fiber| !fiber.is_done());
+        result
+    }
+
+    fn schedule(&mut self, paren: &Token) -> Result<(), RuntimeError> {
+        loop {
+            self.fibers.retain(|fiber| !fiber.is_done());
+            if self.fibers.is_empty() {
+                return Ok(());
+            }
+
+            let mut progressed: bool = false;
+            let mut index: usize = 0;
+            while let Some(fiber) = self.fibers.get(index).cloned() {
+                index += 1;
+                match fiber.resume(self, paren)? {
+                    Turn::Waiting { progressed: false, .. } => {},
+                    _ => progressed = true,
+                }
+            }
+
+            if !progressed {
+                let blocked: Vec<String> = self.fibers.iter()
+                    .map(|fiber| format!("fiber {} ({}) to {}", fiber.id, fiber.name, fiber.waiting_on().unwrap_or("run")))
+                    .collect();
+                return Err(RuntimeError::new(paren, &format!("Deadlock, all fibers are waiting: {}.", blocked.join(", "))));
+            }
+        }
+    }
+
+    // Runs a fiber's calls until it gives way, returning what it waits for, or until its
+    // function returns
+    pub fn run_fiber(&mut self, calls: &mut Vec<Call>) -> Result<Option<Wait>, RuntimeError> {
+        let environment: Rc<RefCell<Environment>> = calls.last().expect("A fiber that can run has a call").frame.environment.clone();
+        let previous: Rc<RefCell<Environment>> = std::mem::replace(&mut self.environment, environment);
+        let in_fiber: bool = std::mem::replace(&mut self.in_fiber, true);
+        let result: Result<Option<Wait>, RuntimeError> = self.run_calls(calls);
+        self.in_fiber = in_fiber;
+        self.environment = previous;
+        result
+    }
+
+    // The environment of the innermost call is the current one while it runs
+    fn run_calls(&mut self, calls: &mut Vec<Call>) -> Result<Option<Wait>, RuntimeError> {
+        loop {
+            let call: &mut Call = calls.last_mut().expect("A running fiber has a call");
+            let code: Rc<Vec<Instruction>> = call.code.clone();
+            let instruction: &Instruction = &code[call.frame.ip];
+            call.frame.ip += 1;
+
+            let result: Result<Option<Wait>, RuntimeError> = match instruction {
+                Instruction::Call { paren, arguments } => self.fiber_call(calls, paren, *arguments),
+                instruction => match self.step(instruction, &mut call.frame) {
+                    Ok(Step::Next) => Ok(None),
+                    Ok(Step::Finish) => {
+                        self.unwind(&mut call.frame, 0);
+                        let finished: Call = calls.pop().expect("The call that finished is the innermost");
+                        // An initializer always hands back the instance, even on an early return
+                        let value: Object = if finished.function.is_initializer {
+                            finished.function.closure.borrow().get_here("this").unwrap_or(Object::Null)
+                        } else {
+                            finished.frame.returned.unwrap_or(Object::Null)
+                        };
+
+                        let Some(caller) = calls.last_mut() else {
+                            return Ok(None);
+                        };
+                        self.environment = caller.frame.environment.clone();
+                        caller.frame.stack.push(Slot::Value(value));
+                        Ok(None)
+                    },
+                    Ok(Step::Yield(_)) => unreachable!("A fiber's calls never yield, calling a generator only makes one"),
+                    Err(error) => Err(error),
+                },
+            };
+
+            match result {
+                Ok(None) => {},
+                Ok(Some(wait)) => {
+                    calls.last_mut().expect("The call that gave way is still there").frame.environment = self.environment.clone();
+                    return Ok(Some(wait));
+                },
+                Err(error) => self.catch_in_fiber(calls, error)?,
+            }
+        }
+    }
+
+    // A call made by a fiber's code. Lox functions get a frame of their own in the fiber instead
+    // of a nested call, so the fiber can give way inside them.
+    fn fiber_call(&mut self, calls: &mut Vec<Call>, paren: &Token, arguments: usize) -> Result<Option<Wait>, RuntimeError> {
+        let frame: &mut Frame = &mut calls.last_mut().expect("A running fiber has a call").frame;
+        let arguments: Vec<Object> = Self::pop_values(frame, arguments);
+        let callee: Object = Self::pop_value(frame);
+
+        let function: Rc<Function> = match callee {
+            Object::Function(function) if function.declaration.generator.is_none() => {
+                Self::check_arity(paren, function.arity(), arguments.len())?;
+                function
+            },
+            Object::Class(class) => {
+                Self::check_arity(paren, class.arity(), arguments.len())?;
+                let instance: Rc<RefCell<Instance>> = Rc::new(RefCell::new(Instance::new(class.clone())));
+                match class.find_method("init") {
+                    Some(initializer) => Rc::new(initializer.bind(instance)),
+                    None => {
+                        frame.stack.push(Slot::Value(Object::Instance(instance)));
+                        return Ok(None);
+                    },
+                }
+            },
+            Object::NativeFunction(function) => {
+                Self::check_arity(paren, function.arity, arguments.len())?;
+                let body = function.body.clone();
+                let result: Result<Object, RuntimeError> = self.nested(paren, |interpreter| {
+                    interpreter.can_give_way = true;
+                    body(interpreter, paren, arguments)
+                });
+                // The value of a call that gave way comes when the fiber carries on
+                let wait: Option<Wait> = self.giving_way.take();
+                let value: Object = result?;
+                if wait.is_none() {
+                    frame.stack.push(Slot::Value(value));
+                }
+                return Ok(wait);
+            },
+            // Calling a generator function only makes the generator, and anything else is an error
+            callee => {
+                let value: Object = self.call(callee, paren, arguments)?;
+                frame.stack.push(Slot::Value(value));
+                return Ok(None);
+            },
+        };
+
+        // The frames are on the heap, but a runaway recursion would still use up all the memory
+        if calls.len() == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(paren, "Stack overflow."));
+        }
+        let call: Call = Call::new(function, arguments);
+        let caller: &mut Call = calls.last_mut().expect("A running fiber has a call");
+        caller.frame.environment = std::mem::replace(&mut self.environment, call.frame.environment.clone());
+        calls.push(call);
+        Ok(None)
+    }
+
+    // The innermost try around the error gets it, going out of the calls without one
+    fn catch_in_fiber(&mut self, calls: &mut Vec<Call>, error: RuntimeError) -> Result<(), RuntimeError> {
+        while let Some(call) = calls.last_mut() {
+            if let Some(target) = self.unwind_to_handler(&mut call.frame) {
+                call.frame.caught = Some(error);
+                call.frame.ip = target;
+                return Ok(());
+            }
+
+            calls.pop();
+            if let Some(caller) = calls.last() {
+                self.environment = caller.frame.environment.clone();
+            }
+        }
+        Err(error)
+    }
+
+    // For the native functions that make a fiber give way, 'message' is the error outside of one
+    pub fn check_give_way(&self, paren: &Token, message: &str) -> Result<(), RuntimeError> {
+        if !self.in_fiber {
+            return Err(RuntimeError::new(paren, message));
+        }
+        if !self.can_give_way {
+            return Err(RuntimeError::new(paren, "A fiber can't give way from inside a callback, getter or generator."));
+        }
+        Ok(())
+    }
+
+    // Called by a native function after 'check_give_way', the fiber gives way once it returns
+    pub fn give_way(&mut self, wait: Wait) {
+        self.giving_way = Some(wait);
+    }
+
+    fn pop_value(frame: &mut Frame) -> Object {
+        match frame.stack.pop() {
+            Some(Slot::Value(value)) => value,
+            _ => unreachable!("Instructions only pop the values pushed for them"),
+        }
+    }
+
+    // The last 'count' values pushed, in the order they were pushed
+    fn pop_values(frame: &mut Frame, count: usize) -> Vec<Object> {
+        let values: Vec<Slot> = frame.stack.split_off(frame.stack.len() - count);
+        values.into_iter().map(|slot| match slot {
+            Slot::Value(value) => value,
+            _ => unreachable!("Instructions only pop the values pushed for them"),
+        }).collect()
+    }
+
+    fn step(&mut self, instruction: &Instruction, frame: &mut Frame) -> Result<Step, RuntimeError> {
+        match instruction {
+            Instruction::Execute { statement, exits } => match self.execute(statement) {
+                Ok(()) => {},
+                Err(Interrupt::Error(error)) => return Err(error),
+                Err(interrupt) => {
+                    let exits = exits.expect("Statements that can break, continue or return have exits");
+                    frame.ip = match interrupt {
+                        Interrupt::Break => exits.on_break,
+                        Interrupt::Continue => exits.on_continue,
+                        Interrupt::Return(value) => {
+                            frame.returned = Some(value);
+                            Some(exits.on_return)
+                        },
+                        Interrupt::Error(_) => unreachable!("Errors are returned above"),
+                    }.expect("The resolver rejects 'break' and 'continue' outside of a loop");
+                },
+            },
+            Instruction::Push { expression } => {
+                let value: Object = self.evaluate(expression)?;
+                frame.stack.push(Slot::Value(value));
+            },
+            Instruction::Apply { expression } => {
+                let operands: Vec<Object> = Self::pop_values(frame, expression.operands().len());
+                let value: Object = self.evaluate(&expression.with_operands(operands))?;
+                frame.stack.push(Slot::Value(value));
+            },
+            Instruction::Call { .. } => unreachable!("Only a fiber's code has calls, and the fiber makes them itself"),
+            Instruction::Yield => return Ok(Step::Yield(Self::pop_value(frame))),
+            Instruction::JumpUnless { target } => {
+                let condition: Object = Self::pop_value(frame);
+                if !Self::is_truthy(&condition) {
+                    frame.ip = *target;
+                }
+            },
+            Instruction::JumpKeeping { when, target } => {
+                let Some(Slot::Value(value)) = frame.stack.last() else {
+                    unreachable!("JumpKeeping runs with the left operand on top of the stack");
+                };
+                if Self::is_truthy(value) == *when {
+                    frame.ip = *target;
+                } else {
+                    frame.stack.pop();
+                }
+            },
+            Instruction::Jump { target } => frame.ip = *target,
+            Instruction::BeginScope => self.begin_generator_scope(frame),
+            Instruction::EndScope => self.unwind(frame, frame.stack.len() - 1),
+            Instruction::IterStart { keyword } => {
+                let iterable: Object = Self::pop_value(frame);
+                let iteration: Iteration = self.iteration(keyword, iterable)?;
+                frame.stack.push(Slot::Iteration(iteration));
+            },
+            Instruction::IterNext { keyword, name, exit } => {
+                let Some(Slot::Iteration(iteration)) = frame.stack.last_mut() else {
+                    unreachable!("IterNext runs with its iteration on top of the stack");
+                };
+                match self.next_value(keyword, iteration)? {
+                    Some(value) => {
+                        self.begin_generator_scope(frame);
+                        self.environment.borrow_mut().define(&name.lexeme, value);
+                    },
+                    None => {
+                        frame.stack.pop();
+                        frame.ip = *exit;
+                    },
+                }
+            },
+            Instruction::PushHandler { target } => frame.stack.push(Slot::Handler(*target)),
+            Instruction::PopHandler => {
+                frame.stack.pop();
+            },
+            Instruction::Catch { name } => {
+                let error: RuntimeError = frame.caught.take().expect("Catch runs at a handler");
+                self.begin_generator_scope(frame);
+                let error: Object = self.error_object(error);
+                self.environment.borrow_mut().define(&name.lexeme, error);
+            },
+            Instruction::SaveError => {
+                let error: RuntimeError = frame.caught.take().expect("SaveError runs at a handler");
+                frame.stack.push(Slot::Error(error));
+            },
+            Instruction::Rethrow => match frame.stack.pop() {
+                Some(Slot::Error(error)) => return Err(error),
+                _ => unreachable!("Rethrow runs with the saved error on top of the stack"),
+            },
+            Instruction::MatchArm { pattern, next } => {
+                let Some(Slot::Value(value)) = frame.stack.last() else {
+                    unreachable!("MatchArm runs with the matched value on top of the stack");
+                };
+                let value: Object = value.clone();
+
+                self.begin_generator_scope(frame);
+                if !self.match_pattern(pattern, &value)? {
+                    self.unwind(frame, frame.stack.len() - 1);
+                    frame.ip = *next;
+                }
+            },
+            Instruction::PopValue => {
+                frame.stack.pop();
+            },
+            Instruction::Define { name } => {
+                let value: Object = Self::pop_value(frame);
+                self.environment.borrow_mut().define(&name.lexeme, value);
+            },
+            Instruction::Print => {
+                let value: Object = Self::pop_value(frame);
+                self.print(&value);
+            },
+            Instruction::Throw { keyword } => return Err(RuntimeError::thrown(keyword, Self::pop_value(frame))),
+            Instruction::Return => frame.returned = Some(Self::pop_value(frame)),
+            Instruction::Unwind { height } => self.unwind(frame, *height),
+            Instruction::Finish => return Ok(Step::Finish),
+        }
+        Ok(Step::Next)
+    }
+
+    fn print(&mut self, value: &Object) {
+        writeln!(self.output.borrow_mut(), "{}", Self::stringify(value)).expect("Failed to write output.");
+    }
+
+    fn begin_generator_scope(&mut self, frame: &mut Frame) {
+        let environment: Rc<RefCell<Environment>> = Rc::new(RefCell::new(Environment::with_enclosing(self.environment.clone())));
+        frame.stack.push(Slot::Scope(std::mem::replace(&mut self.environment, environment)));
+    }
+
+    // Drops slots down to 'height', going back out of the scopes on the way
+    fn unwind(&mut self, frame: &mut Frame, height: usize) {
+        while frame.stack.len() > height {
+            if let Some(Slot::Scope(previous)) = frame.stack.pop() {
+                self.environment = previous;
+            }
+        }
+    }
+
+    // Drops slots down to the innermost handler, which is dropped too, and returns where it goes
+    fn unwind_to_handler(&mut self, frame: &mut Frame) -> Option<usize> {
+        while let Some(slot) = frame.stack.pop() {
+            match slot {
+                Slot::Scope(previous) => self.environment = previous,
+                Slot::Handler(target) => return Some(target),
+                _ => {},
+            }
+        }
+        None
+    }
+
+    // The value a catch clause binds, a thrown value as is or an Error instance for built-in errors
+    fn error_object(&self, error: RuntimeError) -> Object {
+        if let Some(value) = error.value {
+            return value;
+        }
+
+        let mut instance: Instance = Instance::new(self.error_class.clone());
+        instance.fields.insert("message".to_string(), Object::String(error.message));
+        instance.fields.insert("line".to_string(), Object::Integer(error.token.line as i64));
+        Object::Instance(Rc::new(RefCell::new(instance)))
+    }
+
+    // Whether 'value' matches, the names the pattern binds are defined in the current environment
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Object) -> Result<bool, RuntimeError> {
+        match pattern {
+            Pattern::Literal { value: literal } => Ok(literal == value),
+            Pattern::Range { range } => Ok(value.as_float().is_some_and(|number| range.contains(number))),
+            Pattern::Binding { name } => {
+                self.environment.borrow_mut().define(&name.lexeme, value.clone());
+                Ok(true)
+            },
+            Pattern::Wildcard => Ok(true),
+            Pattern::List { elements, rest } => {
+                let Object::List(list) = value else {
+                    return Ok(false);
+                };
+                let values: Vec<Object> = list.borrow().elements.clone();
+
+                let length_matches: bool = if rest.is_some() { values.len() >= elements.len() } else { values.len() == elements.len() };
+                if !length_matches {
+                    return Ok(false);
+                }
+
+                for (element, value) in elements.iter().zip(&values) {
+                    if !self.match_pattern(element, value)? {
+                        return Ok(false);
+                    }
+                }
+
+                match rest {
+                    Some(rest) => {
+                        let remaining: Object = Object::List(Rc::new(RefCell::new(List::new(values[elements.len()..].to_vec()))));
+                        self.match_pattern(rest, &remaining)
+                    },
+                    None => Ok(true),
+                }
+            },
+            Pattern::Instance { class, fields } => {
+                let class: Rc<Class> = match self.evaluate(class)? {
+                    Object::Class(class) => class,
+                    _ => {
+                        let token: &Token = match class {
+                            Expression::Variable { name, .. } => name,
+                            _ => unreachable!("Instance patterns name their class with a variable"),
+                        };
+                        return Err(RuntimeError::new(token, "Instance pattern must name a class."));
+                    },
+                };
+
+                let Object::Instance(instance) = value else {
+                    return Ok(false);
+                };
+                if !instance.borrow().class.is_subclass_of(&class) {
+                    return Ok(false);
+                }
+
+                for (name, field) in fields {
+                    let field_value: Option<Object> = instance.borrow().fields.get(&name.lexeme).cloned();
+                    match field_value {
+                        Some(field_value) if self.match_pattern(field, &field_value)? => {},
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            },
+        }
+    }
+
+    // Runs the arm if it matches, in the environment that holds its bindings
+    fn execute_arm(&mut self, arm: &MatchArm, value: &Object) -> Result<bool, Interrupt> {
+        if !self.match_pattern(&arm.pattern, value)? {
+            return Ok(false);
+        }
+        if let Some(guard) = &arm.guard {
+            let guard: Object = self.evaluate(guard)?;
+            if !Self::is_truthy(&guard) {
+                return Ok(false);
+            }
+        }
+
+        self.execute(&arm.body)?;
+        Ok(true)
+    }
+
+    fn look_up_variable(&self, id: usize, name: &Token) -> Result<Object, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(distance) => Environment::get_at(&self.environment, *distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    // Helpers
+    pub fn stringify(value: &Object) -> String {
+        value.to_string()
+    }
+
+    pub fn is_truthy(value: &Object) -> bool {
+        match value {
+            Object::Null => false,
+            Object::Boolean(boolean) => *boolean,
+            _ => true,
+        }
+    }
+
+    fn number_operands(operator: &Token, left: &Object, right: &Object) -> Result<(f64, f64), RuntimeError> {
+        match (left.as_float(), right.as_float()) {
+            (Some(left), Some(right)) => Ok((left, right)),
+            _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+        }
+    }
+
+    fn integer_operand(operator: &Token, operand: &Object) -> Result<i64, RuntimeError> {
+        match operand.as_integer() {
+            Some(integer) => Ok(integer),
+            None => Err(RuntimeError::new(operator, "Operand must be an integer.")),
+        }
+    }
+
+    fn integer_operands(operator: &Token, left: &Object, right: &Object) -> Result<(i64, i64), RuntimeError> {
+        match (left.as_integer(), right.as_integer()) {
+            (Some(left), Some(right)) => Ok((left, right)),
+            _ => Err(RuntimeError::new(operator, "Operands must be integers.")),
+        }
+    }
+
+    // Integers and rationals compare exactly, anything with a float as floats where NaN is unordered
+    fn comparison(operator: &Token, left: &Object, right: &Object) -> Result<Object, RuntimeError> {
+        let ordering: Option<Ordering> = match (left, right) {
+            (Object::Integer(left), Object::Integer(right)) => Some(left.cmp(right)),
+            (Object::Number(left), Object::Number(right)) => left.partial_cmp(right),
+            (Object::Number(number), exact) => Self::float_ordering(operator, *number, exact)?,
+            (exact, Object::Number(number)) => Self::float_ordering(operator, *number, exact)?.map(Ordering::reverse),
+            _ => match (left.as_rational(), right.as_rational()) {
+                (Some(left), Some(right)) => Some(left.cmp(&right)),
+                _ => return Err(RuntimeError::new(operator, "Operands must be numbers.")),
+            },
+        };
+
+        let result: bool = match operator.token_type {
+            TokenType::Greater => ordering == Some(Ordering::Greater),
+            TokenType::GreaterEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            TokenType::Less => ordering == Some(Ordering::Less),
+            TokenType::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            _ => return Err(RuntimeError::new(operator, "Unknown comparison operator.")),
+        };
+        Ok(Object::Boolean(result))
+    }
+
+    // How a float orders against an integer or rational, None for NaN. Integers up to 2 ** 53 are
+    // floats already, past that the float converts to an exact fraction so no digits get rounded.
+    fn float_ordering(operator: &Token, number: f64, exact: &Object) -> Result<Option<Ordering>, RuntimeError> {
+        if let Object::Integer(integer) = exact {
+            if integer.unsigned_abs() <= 1 << 53 {
+                return Ok(number.partial_cmp(&(*integer as f64)));
+            }
+        }
+
+        let Some(exact) = exact.as_rational() else {
+            return Err(RuntimeError::new(operator, "Operands must be numbers."));
+        };
+        if number.is_nan() {
+            return Ok(None);
+        }
+        match Rational::from_f64(number) {
+            Some(number) => Ok(Some(number.cmp(&exact))),
+            // Infinities are past every exact value
+            None if number > 0.0 => Ok(Some(Ordering::Greater)),
+            None => Ok(Some(Ordering::Less)),
+        }
+    }
+
+    fn bitwise(operator: &Token, left: &Object, right: &Object) -> Result<Object, RuntimeError> {
+        let (left, right) = Self::integer_operands(operator, left, right)?;
+
+        let result: i64 = match operator.token_type {
+            TokenType::Ampersand => left & right,
+            TokenType::Pipe => left | right,
+            TokenType::Caret => left ^ right,
+            TokenType::LessLess | TokenType::GreaterGreater => {
+                let shift: u32 = match u32::try_from(right) {
+                    Ok(shift) if shift < i64::BITS => shift,
+                    _ => return Err(RuntimeError::new(operator, "Shift amount must be between 0 and 63.")),
+                };
+                if operator.token_type == TokenType::LessLess { left << shift } else { left >> shift }
+            },
+            _ => return Err(RuntimeError::new(operator, "Unknown bitwise operator.")),
+        };
+        Ok(Object::Integer(result))
+    }
+
+    // The arithmetic shared by binary operators and their compound assignment forms. Two
+    // integers stay integers, rationals stay exact with integers and rationals, and anything
+    // mixed with a float becomes a float.
+    fn arithmetic(operator: &Token, left: &Object, right: &Object) -> Result<Object, RuntimeError> {
+        match (left, right) {
+            (Object::Integer(left), Object::Integer(right)) => return Self::integer_arithmetic(operator, *left, *right),
+            (Object::Integer(_) | Object::BigInt(_), Object::Integer(_) | Object::BigInt(_)) => {
+                return Self::big_int_arithmetic(operator, &left.as_big_int().unwrap(), &right.as_big_int().unwrap());
+            },
+            (Object::Rational(_), _) | (_, Object::Rational(_)) => {
+                if let (Some(left), Some(right)) = (left.as_rational(), right.as_rational()) {
+                    return Self::rational_arithmetic(operator, &left, &right);
+                }
+            },
+            _ => {},
+        }
+
+        match operator.token_type {
+            TokenType::Plus | TokenType::PlusEqual | TokenType::PlusPlus => match (left, right) {
+                (Object::String(left), Object::String(right)) => Ok(Object::String(left.clone() + right)),
+                _ => match (left.as_float(), right.as_float()) {
+                    (Some(left), Some(right)) => Ok(Object::Number(left + right)),
+                    _ => Err(RuntimeError::new(operator, "Operands must be two numbers or two strings.")),
+                },
+            },
+            TokenType::Minus | TokenType::MinusEqual | TokenType::MinusMinus => {
+                let (left, right) = Self::number_operands(operator, left, right)?;
+                Ok(Object::Number(left - right))
+            },
+            TokenType::Star | TokenType::StarEqual => {
+                let (left, right) = Self::number_operands(operator, left, right)?;
+                Ok(Object::Number(left * right))
+            },
+            TokenType::Slash | TokenType::SlashEqual => {
+                let (left, right) = Self::number_operands(operator, left, right)?;
+                Ok(Object::Number(left / right))
+            },
+            TokenType::Percent | TokenType::PercentEqual => {
+                let (left, right) = Self::number_operands(operator, left, right)?;
+                Ok(Object::Number(left % right))
+            },
+            TokenType::StarStar => {
+                let (left, right) = Self::number_operands(operator, left, right)?;
+                Ok(Object::Number(left.powf(right)))
+            },
+            _ => Err(RuntimeError::new(operator, "Unknown arithmetic operator.")),
+        }
+    }
+
+    // Division is exact or gives a float, '%' takes the sign of the left operand like the float
+    // remainder does, and results that overflow the i64 range carry on as big integers
+    fn integer_arithmetic(operator: &Token, left: i64, right: i64) -> Result<Object, RuntimeError> {
+        let result: Option<i64> = match operator.token_type {
+            TokenType::Plus | TokenType::PlusEqual | TokenType::PlusPlus => left.checked_add(right),
+            TokenType::Minus | TokenType::MinusEqual | TokenType::MinusMinus => left.checked_sub(right),
+            TokenType::Star | TokenType::StarEqual => left.checked_mul(right),
+            TokenType::Slash | TokenType::SlashEqual => {
+                if right == 0 {
+                    return Err(RuntimeError::new(operator, "Division by zero."));
+                }
+                // Only i64::MIN / -1 has no remainder in range, and its quotient overflows too
+                match left.checked_rem(right) {
+                    Some(0) => left.checked_div(right),
+                    Some(_) => return Ok(Object::Number(left as f64 / right as f64)),
+                    None => None,
+                }
+            },
+            TokenType::Percent | TokenType::PercentEqual => {
+                if right == 0 {
+                    return Err(RuntimeError::new(operator, "Division by zero."));
+                }
+                Some(left.wrapping_rem(right))
+            },
+            TokenType::StarStar => match u32::try_from(right) {
+                Ok(exponent) => left.checked_pow(exponent),
+                // A negative power is a fraction
+                Err(_) if right < 0 => return Ok(Object::Number((left as f64).powf(right as f64))),
+                Err(_) => None,
+            },
+            _ => return Err(RuntimeError::new(operator, "Unknown arithmetic operator.")),
+        };
+
+        match result {
+            Some(result) => Ok(Object::Integer(result)),
+            None => Self::big_int_arithmetic(operator, &BigInt::from(left), &BigInt::from(right)),
+        }
+    }
+
+    // The same rules as for i64, for operands or results out of its range
+    fn big_int_arithmetic(operator: &Token, left: &BigInt, right: &BigInt) -> Result<Object, RuntimeError> {
+        let result: BigInt = match operator.token_type {
+            TokenType::Plus | TokenType::PlusEqual | TokenType::PlusPlus => left.add(right),
+            TokenType::Minus | TokenType::MinusEqual | TokenType::MinusMinus => left.subtract(right),
+            TokenType::Star | TokenType::StarEqual => left.multiply(right),
+            TokenType::Slash | TokenType::SlashEqual | TokenType::Percent | TokenType::PercentEqual => {
+                let Some((quotient, remainder)) = left.divide(right) else {
+                    return Err(RuntimeError::new(operator, "Division by zero."));
+                };
+                match operator.token_type {
+                    TokenType::Percent | TokenType::PercentEqual => remainder,
+                    _ if remainder.is_zero() => quotient,
+                    _ => return Ok(Object::Number(left.to_f64() / right.to_f64())),
+                }
+            },
+            // A negative power is a fraction
+            TokenType::StarStar if right.is_negative() => return Ok(Object::Number(left.to_f64().powf(right.to_f64()))),
+            TokenType::StarStar => match left.pow(right) {
+                Some(power) => power,
+                None => return Err(RuntimeError::new(operator, "Exponent is too large.")),
+            },
+            _ => return Err(RuntimeError::new(operator, "Unknown arithmetic operator.")),
+        };
+        Ok(Object::from_big_int(result))
+    }
+
+    // Exact for every operator, only a fractional power gives a float
+    fn rational_arithmetic(operator: &Token, left: &Rational, right: &Rational) -> Result<Object, RuntimeError> {
+        let result: Option<Rational> = match operator.token_type {
+            TokenType::Plus | TokenType::PlusEqual | TokenType::PlusPlus => Some(left.add(right)),
+            TokenType::Minus | TokenType::MinusEqual | TokenType::MinusMinus => Some(left.subtract(right)),
+            TokenType::Star | TokenType::StarEqual => Some(left.multiply(right)),
+            TokenType::Slash | TokenType::SlashEqual => left.divide(right),
+            TokenType::Percent | TokenType::PercentEqual => left.remainder(right),
+            TokenType::StarStar => {
+                if !right.is_integer() {
+                    return Ok(Object::Number(left.to_f64().powf(right.to_f64())));
+                }
+                // A negative power of zero divides by zero
+                if left.numerator().is_zero() && right.numerator().is_negative() {
+                    None
+                } else {
+                    match left.pow(right.numerator()) {
+                        Some(power) => Some(power),
+                        None => return Err(RuntimeError::new(operator, "Exponent is too large.")),
+                    }
+                }
+            },
+            _ => return Err(RuntimeError::new(operator, "Unknown arithmetic operator.")),
+        };
+
+        match result {
+            Some(result) => Ok(Object::Rational(result)),
+            None => Err(RuntimeError::new(operator, "Division by zero.")),
+        }
+    }
+
+    // Reads 'object[index]'
+    fn index_get(bracket: &Token, object: &Object, index: &Object) -> Result<Object, RuntimeError> {
+        match object {
+            Object::List(list) => match index {
+                Object::Range(range) => Ok(Object::List(Rc::new(RefCell::new(list.borrow().slice_range(range))))),
+                _ => list.borrow().get(bracket, index),
+            },
+            Object::Map(map) => map.borrow().get(bracket, index),
+            _ => Err(RuntimeError::new(bracket, "Only lists and maps can be indexed.")),
+        }
+    }
+
+    // Writes 'object[index] = value'
+    fn index_set(bracket: &Token, object: &Object, index: &Object, value: Object) -> Result<(), RuntimeError> {
+        match object {
+            Object::List(list) => list.borrow_mut().set(bracket, index, value),
+            Object::Map(map) => map.borrow_mut().set(bracket, index.clone(), value),
+            _ => Err(RuntimeError::new(bracket, "Only lists and maps can be indexed.")),
+        }
+    }
+
+    // A field, the result of a getter, or a method bound to its object
+    fn get_property(&mut self, object: Object, name: &Token) -> Result<Object, RuntimeError> {
+        let instance: Rc<RefCell<Instance>> = match object {
+            Object::Instance(instance) => instance,
+            Object::Class(class) => {
+                if let Some(method) = class.find_class_method(&name.lexeme) {
+                    return Ok(Object::Function(method));
+                }
+                return match class.native_methods.get(&name.lexeme) {
+                    Some(method) => Ok(Object::NativeFunction(method.clone())),
+                    None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+                };
+            },
+            Object::List(list) => return Self::built_in_method(name, List::method(&list, name)),
+            Object::Map(map) => return Self::built_in_method(name, Map::method(&map, name)),
+            Object::Set(set) => return Self::built_in_method(name, Set::method(&set, name)),
+            Object::Range(range) => return Self::built_in_method(name, Range::method(range, name)),
+            Object::Generator(generator) => return Self::built_in_method(name, Generator::method(&generator, name)),
+            Object::Fiber(fiber) => return Self::built_in_method(name, Fiber::method(&fiber, name)),
+            Object::Channel(channel) => return Self::built_in_method(name, Channel::method(&channel, name)),
+            Object::Rational(rational) => return Self::built_in_method(name, Rational::method(&rational, name)),
+            _ => return Err(RuntimeError::new(name, "Only instances have properties.")),
+        };
+
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let getter: Option<Rc<Function>> = instance.borrow().class.find_getter(&name.lexeme);
+        if let Some(getter) = getter {
+            return self.call_function(&getter.bind(instance), name, Vec::new());
+        }
+
+        let method: Option<Rc<Function>> = instance.borrow().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => Ok(Object::Function(Rc::new(method.bind(instance)))),
+            None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        }
+    }
+
+    // The traits named in a 'with' clause
+    fn traits(&mut self, names: &[Expression]) -> Result<Vec<Rc<Class>>, RuntimeError> {
+        let mut traits: Vec<Rc<Class>> = Vec::new();
+        for expression in names {
+            match self.evaluate(expression)? {
+                Object::Trait(mixed) => traits.push(mixed),
+                _ => {
+                    let Expression::Variable { name, .. } = expression else {
+                        unreachable!("Traits are named with a variable");
+                    };
+                    return Err(RuntimeError::new(name, "Can only mix in traits."));
+                },
+            }
+        }
+        Ok(traits)
+    }
+
+    // Puts each method of a class or trait body in the table for its kind
+    fn add_methods(class: &mut Class, methods: &[Method], closure: &Rc<RefCell<Environment>>) {
+        for method in methods {
+            let declaration: &Rc<FunctionDeclaration> = &method.declaration;
+            let is_initializer: bool = method.kind == MethodKind::Instance && declaration.name.lexeme == "init";
+            let function: Function = Function::new(declaration.clone(), closure.clone(), is_initializer);
+
+            let table: &mut HashMap<String, Rc<Function>> = match method.kind {
+                MethodKind::Instance => &mut class.methods,
+                MethodKind::Static => &mut class.class_methods,
+                MethodKind::Getter => &mut class.getters,
+                MethodKind::Setter => &mut class.setters,
+            };
+            table.insert(declaration.name.lexeme.clone(), Rc::new(function));
+        }
+    }
+
+    // Runs the setter for 'name' if the class has one, otherwise stores a field. A getter without a
+    // setter is read-only, a field of the same name would hide it.
+    fn set_property(&mut self, instance: Rc<RefCell<Instance>>, name: &Token, value: Object) -> Result<(), RuntimeError> {
+        let setter: Option<Rc<Function>> = instance.borrow().class.find_setter(&name.lexeme);
+        match setter {
+            Some(setter) => {
+                self.call_function(&setter.bind(instance), name, vec![value])?;
+            },
+            None if instance.borrow().class.find_getter(&name.lexeme).is_some() => {
+                return Err(RuntimeError::new(name, &format!("Property '{}' has a getter but no setter.", name.lexeme)));
+            },
+            None => {
+                instance.borrow_mut().fields.insert(name.lexeme.clone(), value);
+            },
+        }
+        Ok(())
+    }
+
+    // Lists, maps and the other built-in types look up their methods with a 'method(value, name)'
+    // function, which hands back the native with the value already bound, like a bound method of
+    // an instance
+    fn built_in_method(name: &Token, method: Option<NativeFunction>) -> Result<Object, RuntimeError> {
+        match method {
+            Some(method) => Ok(Object::NativeFunction(Rc::new(method))),
+            None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        }
+    }
+
+    fn instance_operand(name: &Token, object: Object) -> Result<Rc<RefCell<Instance>>, RuntimeError> {
+        match object {
+            Object::Instance(instance) => Ok(instance),
+            _ => Err(RuntimeError::new(name, "Only instances have fields.")),
+        }
+    }
+}
+
+impl ExpressionVisitor<Result<Object, RuntimeError>> for Interpreter {
+    fn visit_assign_expression(&mut self, id: usize, name: &Token, value: &Expression) -> Result<Object, RuntimeError> {
+        let value: Object = self.evaluate(value)?;
+        match self.locals.get(&id) {
+            Some(distance) => Environment::assign_at(&self.environment, *distance, name, value.clone()),
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_binary_expression(&mut self, left: &Expression, operator: &Token, right: &Expression) -> Result<Object, RuntimeError> {
+        let left: Object = self.evaluate(left)?;
+        let right: Object = self.evaluate(right)?;
+
+        match operator.token_type {
+            // Both sides are evaluated in order, the left value is discarded
+            TokenType::Comma => Ok(right),
+            TokenType::BangEqual => Ok(Object::Boolean(left != right)),
+            TokenType::EqualEqual => Ok(Object::Boolean(left == right)),
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => Self::comparison(operator, &left, &right),
+            TokenType::Ampersand | TokenType::Pipe | TokenType::Caret | TokenType::LessLess | TokenType::GreaterGreater => Self::bitwise(operator, &left, &right),
+            TokenType::DotDot | TokenType::DotDotEqual => {
+                let (start, end) = Self::integer_operands(operator, &left, &right)?;
+                Ok(Object::Range(Range::new(start, end, operator.token_type == TokenType::DotDotEqual)))
+            },
+            _ => Self::arithmetic(operator, &left, &right),
+        }
+    }
+
+    fn visit_call_expression(&mut self, callee: &Expression, paren: &Token, arguments: &[Expression]) -> Result<Object, RuntimeError> {
+        let callee: Object = self.evaluate(callee)?;
+
+        let mut values: Vec<Object> = Vec::new();
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        self.call(callee, paren, values)
+    }
+
+    fn visit_compound_assign_expression(&mut self, id: usize, name: &Token, operator: &Token, value: &Expression, postfix: bool) -> Result<Object, RuntimeError> {
+        // The variable is looked up once and written back to the same place
+        let environment: Rc<RefCell<Environment>> = match self.locals.get(&id) {
+            Some(distance) => Environment::ancestor(&self.environment, *distance),
+            None => self.globals.clone(),
+        };
+        let old: Object = environment.borrow().get(name)?;
+
+        let value: Object = self.evaluate(value)?;
+        let new: Object = Self::arithmetic(operator, &old, &value)?;
+        environment.borrow_mut().define(&name.lexeme, new.clone());
+
+        Ok(if postfix { old } else { new })
+    }
+
+    fn visit_compound_set_expression(&mut self, object: &Expression, name: &Token, operator: &Token, value: &Expression, postfix: bool) -> Result<Object, RuntimeError> {
+        // The object expression is evaluated once, 'f().x += 1' calls 'f' a single time
+        let instance: Rc<RefCell<Instance>> = Self::instance_operand(name, self.evaluate(object)?)?;
+        let field: Option<Object> = instance.borrow().fields.get(&name.lexeme).cloned();
+        let getter: Option<Rc<Function>> = instance.borrow().class.find_getter(&name.lexeme);
+        let old: Object = match (field, getter) {
+            (Some(old), _) => old,
+            (None, Some(getter)) => self.call_function(&getter.bind(instance.clone()), name, Vec::new())?,
+            (None, None) => return Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        };
+
+        let value: Object = self.evaluate(value)?;
+        let new: Object = Self::arithmetic(operator, &old, &value)?;
+        self.set_property(instance, name, new.clone())?;
+
+        Ok(if postfix { old } else { new })
+    }
+
+    fn visit_compound_set_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression, operator: &Token, value: &Expression, postfix: bool) -> Result<Object, RuntimeError> {
+        // The object and index are evaluated once, like 'CompoundSet'
+        let object: Object = self.evaluate(object)?;
+        let index: Object = self.evaluate(index)?;
+        let old: Object = Self::index_get(bracket, &object, &index)?;
+
+        let value: Object = self.evaluate(value)?;
+        let new: Object = Self::arithmetic(operator, &old, &value)?;
+        Self::index_set(bracket, &object, &index, new.clone())?;
+
+        Ok(if postfix { old } else { new })
+    }
+
+    fn visit_conditional_expression(&mut self, condition: &Expression, then_branch: &Expression, else_branch: &Expression) -> Result<Object, RuntimeError> {
+        let condition: Object = self.evaluate(condition)?;
+        if Self::is_truthy(&condition) {
+            self.evaluate(then_branch)
+        } else {
+            self.evaluate(else_branch)
+        }
+    }
+
+    fn visit_function_expression(&mut self, declaration: &Rc<FunctionDeclaration>) -> Result<Object, RuntimeError> {
+        Ok(Object::Function(Rc::new(Function::new(declaration.clone(), self.environment.clone(), false))))
+    }
+
+    fn visit_get_expression(&mut self, object: &Expression, name: &Token) -> Result<Object, RuntimeError> {
+        let object: Object = self.evaluate(object)?;
+        self.get_property(object, name)
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &Expression) -> Result<Object, RuntimeError> {
+        self.evaluate(expression)
+    }
+
+    fn visit_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression) -> Result<Object, RuntimeError> {
+        let object: Object = self.evaluate(object)?;
+        let index: Object = self.evaluate(index)?;
+        Self::index_get(bracket, &object, &index)
+    }
+
+    fn visit_interpolation_expression(&mut self, parts: &[Expression]) -> Result<Object, RuntimeError> {
+        let mut result: String = String::new();
+        for part in parts {
+            let value: Object = self.evaluate(part)?;
+            result.push_str(&Self::stringify(&value));
+        }
+        Ok(Object::String(result))
+    }
+
+    fn visit_list_expression(&mut self, elements: &[Expression]) -> Result<Object, RuntimeError> {
+        let mut values: Vec<Object> = Vec::new();
+        for element in elements {
+            values.push(self.evaluate(element)?);
+        }
+        Ok(Object::List(Rc::new(RefCell::new(List::new(values)))))
+    }
+
+    fn visit_literal_expression(&mut self, value: &Object) -> Result<Object, RuntimeError> {
+        Ok(value.clone())
+    }
+
+    fn visit_map_expression(&mut self, brace: &Token, entries: &[(Expression, Expression)]) -> Result<Object, RuntimeError> {
+        let mut map: Map = Map::new();
+        for (key, value) in entries {
+            let key: Object = self.evaluate(key)?;
+            let value: Object = self.evaluate(value)?;
+            map.set(brace, key, value)?;
+        }
+        Ok(Object::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_logical_expression(&mut self, left: &Expression, operator: &Token, right: &Expression) -> Result<Object, RuntimeError> {
+        let left: Object = self.evaluate(left)?;
+
+        if operator.token_type == TokenType::Or {
+            if Self::is_truthy(&left) {
+                return Ok(left);
+            }
+        } else if !Self::is_truthy(&left) {
+            return Ok(left);
+        }
+
+        self.evaluate(right)
+    }
+
+    fn visit_set_expression(&mut self, object: &Expression, name: &Token, value: &Expression) -> Result<Object, RuntimeError> {
+        let instance: Rc<RefCell<Instance>> = Self::instance_operand(name, self.evaluate(object)?)?;
+        let value: Object = self.evaluate(value)?;
+        self.set_property(instance, name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_set_index_expression(&mut self, object: &Expression, bracket: &Token, index: &Expression, value: &Expression) -> Result<Object, RuntimeError> {
+        let object: Object = self.evaluate(object)?;
+        let index: Object = self.evaluate(index)?;
+        let value: Object = self.evaluate(value)?;
+        Self::index_set(bracket, &object, &index, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_super_expression(&mut self, id: usize, keyword: &Token, method: &Token) -> Result<Object, RuntimeError> {
+        let distance: usize = self.locals.get(&id).copied().unwrap_or(0);
+        let superclass: Object = Environment::get_at(&self.environment, distance, keyword)?;
+        // 'this' is bound in the scope just inside the one holding 'super'
+        let this: Token = Token::new(TokenType::This, "this".to_string(), Object::Null, keyword.line);
+        let instance: Object = Environment::get_at(&self.environment, distance.saturating_sub(1), &this)?;
+
+        match (superclass, instance) {
+            (Object::Class(superclass), Object::Instance(instance)) => match superclass.find_method(&method.lexeme) {
+                Some(found) => Ok(Object::Function(Rc::new(found.bind(instance)))),
+                None => Err(RuntimeError::new(method, &format!("Undefined property '{}'.", method.lexeme))),
+            },
+            _ => Err(RuntimeError::new(keyword, "Can't use 'super' outside of a subclass method.")),
+        }
+    }
+
+    fn visit_this_expression(&mut self, id: usize, keyword: &Token) -> Result<Object, RuntimeError> {
+        self.look_up_variable(id, keyword)
+    }
+
+    fn visit_unary_expression(&mut self, operator: &Token, right: &Expression) -> Result<Object, RuntimeError> {
+        let right: Object = self.evaluate(right)?;
+
+        match operator.token_type {
+            TokenType::Bang => Ok(Object::Boolean(!Self::is_truthy(&right))),
+            TokenType::Minus => match right {
+                Object::Integer(integer) => Ok(integer.checked_neg().map_or_else(|| Object::from_big_int(BigInt::from(integer).negate()), Object::Integer)),
+                Object::BigInt(integer) => Ok(Object::from_big_int(integer.negate())),
+                Object::Rational(rational) => Ok(Object::Rational(rational.negate())),
+                Object::Number(number) => Ok(Object::Number(-number)),
+                _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+            },
+            TokenType::Tilde => Ok(Object::Integer(!Self::integer_operand(operator, &right)?)),
+            _ => Err(RuntimeError::new(operator, "Unknown unary operator.")),
+        }
+    }
+
+    fn visit_variable_expression(&mut self, id: usize, name: &Token) -> Result<Object, RuntimeError> {
+        self.look_up_variable(id, name)
+    }
+}
+
+impl StatementVisitor<Result<(), Interrupt>> for Interpreter {
+    fn visit_block_statement(&mut self, statements: &[Statement]) -> Result<(), Interrupt> {
+        let environment: Environment = Environment::with_enclosing(self.environment.clone());
+        self.execute_block(statements, Rc::new(RefCell::new(environment)))
+    }
+
+    fn visit_break_statement(&mut self, _keyword: &Token) -> Result<(), Interrupt> {
+        Err(Interrupt::Break)
+    }
+
+    fn visit_class_statement(&mut self, name: &Token, superclass: Option<&Expression>, traits: &[Expression], methods: &[Method]) -> Result<(), Interrupt> {
+        let superclass: Option<Rc<Class>> = match superclass {
+            Some(expression) => match self.evaluate(expression)? {
+                Object::Class(class) => Some(class),
+                _ => {
+                    let token: &Token = match expression {
+                        Expression::Variable { name, .. } => name,
+                        _ => name,
+                    };
+                    return Err(RuntimeError::new(token, "Superclass must be a class.").into());
+                },
+            },
+            None => None,
+        };
+        let traits: Vec<Rc<Class>> = self.traits(traits)?;
+
+        self.environment.borrow_mut().define(&name.lexeme, Object::Null);
+
+        // Methods of a subclass close over an extra scope holding 'super'
+        let mut closure: Rc<RefCell<Environment>> = self.environment.clone();
+        if let Some(superclass) = &superclass {
+            let mut environment: Environment = Environment::with_enclosing(self.environment.clone());
+            environment.define("super", Object::Class(superclass.clone()));
+            closure = Rc::new(RefCell::new(environment));
+        }
+
+        let mut class: Class = Class::new(&name.lexeme, superclass, HashMap::new());
+        Self::add_methods(&mut class, methods, &closure);
+        class.mix_in(&traits).map_err(|message| RuntimeError::new(name, &message))?;
+
+        self.environment.borrow_mut().assign(name, Object::Class(Rc::new(class)))?;
+        Ok(())
+    }
+
+    fn visit_continue_statement(&mut self, _keyword: &Token) -> Result<(), Interrupt> {
+        Err(Interrupt::Continue)
+    }
+
+    fn visit_expression_statement(&mut self, expression: &Expression) -> Result<(), Interrupt> {
+        self.evaluate(expression)?;
+        Ok(())
+    }
+
+    fn visit_for_in_statement(&mut self, name: &Token, keyword: &Token, iterable: &Expression, body: &Statement) -> Result<(), Interrupt> {
+        let iterable: Object = self.evaluate(iterable)?;
+        let mut iteration: Iteration = self.iteration(keyword, iterable)?;
+
+        while let Some(value) = self.next_value(keyword, &mut iteration)? {
+            // A fresh scope per iteration, so closures capture that iteration's value
+            let mut environment: Environment = Environment::with_enclosing(self.environment.clone());
+            environment.define(&name.lexeme, value);
+
+            match self.execute_block(std::slice::from_ref(body), Rc::new(RefCell::new(environment))) {
+                Ok(()) | Err(Interrupt::Continue) => {},
+                Err(Interrupt::Break) => return Ok(()),
+                Err(interrupt) => return Err(interrupt),
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_function_statement(&mut self, declaration: &Rc<FunctionDeclaration>) -> Result<(), Interrupt> {
+        let function: Function = Function::new(declaration.clone(), self.environment.clone(), false);
+        self.environment.borrow_mut().define(&declaration.name.lexeme, Object::Function(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_if_statement(&mut self, condition: &Expression, then_branch: &Statement, else_branch: Option<&Statement>) -> Result<(), Interrupt> {
+        let condition: Object = self.evaluate(condition)?;
+
+        if Self::is_truthy(&condition) {
+            self.execute(then_branch)?;
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)?;
+        }
+        Ok(())
+    }
+
+    fn visit_match_statement(&mut self, value: &Expression, arms: &[MatchArm]) -> Result<(), Interrupt> {
+        let value: Object = self.evaluate(value)?;
+
+        for arm in arms {
+            let environment: Rc<RefCell<Environment>> = Rc::new(RefCell::new(Environment::with_enclosing(self.environment.clone())));
+            let previous: Rc<RefCell<Environment>> = std::mem::replace(&mut self.environment, environment);
+            let matched: Result<bool, Interrupt> = self.execute_arm(arm, &value);
+            self.environment = previous;
+
+            if matched? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_print_statement(&mut self, expression: &Expression) -> Result<(), Interrupt> {
+        let value: Object = self.evaluate(expression)?;
+        self.print(&value);
+        Ok(())
+    }
+
+    fn visit_return_statement(&mut self, _keyword: &Token, value: Option<&Expression>) -> Result<(), Interrupt> {
+        let value: Object = match value {
+            Some(value) => self.evaluate(value)?,
+            None => Object::Null,
+        };
+        Err(Interrupt::Return(value))
+    }
+
+    fn visit_throw_statement(&mut self, keyword: &Token, value: &Expression) -> Result<(), Interrupt> {
+        let value: Object = self.evaluate(value)?;
+        Err(RuntimeError::thrown(keyword, value).into())
+    }
+
+    fn visit_trait_statement(&mut self, name: &Token, traits: &[Expression], methods: &[Method]) -> Result<(), Interrupt> {
+        let traits: Vec<Rc<Class>> = self.traits(traits)?;
+
+        let mut class: Class = Class::new(&name.lexeme, None, HashMap::new());
+        Self::add_methods(&mut class, methods, &self.environment);
+        class.mix_in(&traits).map_err(|message| RuntimeError::new(name, &message))?;
+
+        self.environment.borrow_mut().define(&name.lexeme, Object::Trait(Rc::new(class)));
+        Ok(())
+    }
+
+    fn visit_try_statement(&mut self, body: &[Statement], catch: Option<&CatchClause>, finally: Option<&[Statement]>) -> Result<(), Interrupt> {
+        let result: Result<(), Interrupt> = match (self.visit_block_statement(body), catch) {
+            (Err(Interrupt::Error(error)), Some(catch)) => {
+                let mut environment: Environment = Environment::with_enclosing(self.environment.clone());
+                environment.define(&catch.name.lexeme, self.error_object(error));
+                self.execute_block(&catch.body, Rc::new(RefCell::new(environment)))
+            },
+            (result, _) => result,
+        };
+
+        // Runs on every way out of the try, and its own return, break or error wins
+        if let Some(finally) = finally {
+            self.visit_block_statement(finally)?;
+        }
+        result
+    }
+
+    fn visit_var_statement(&mut self, name: &Token, initializer: Option<&Expression>) -> Result<(), Interrupt> {
+        let value: Object = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Object::Null,
+        };
+        self.environment.borrow_mut().define(&name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_while_statement(&mut self, condition: &Expression, body: &Statement, increment: Option<&Expression>) -> Result<(), Interrupt> {
+        loop {
+            let value: Object = self.evaluate(condition)?;
+            if !Self::is_truthy(&value) {
+                return Ok(());
+            }
+
+            match self.execute(body) {
+                Ok(()) | Err(Interrupt::Continue) => {},
+                Err(Interrupt::Break) => return Ok(()),
+                Err(interrupt) => return Err(interrupt),
+            }
+
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
+        }
+    }
+
+    // Generator bodies run from their compiled instructions, and the resolver keeps 'yield' out of everything else
+    fn visit_yield_statement(&mut self, keyword: &Token, _value: Option<&Expression>) -> Result<(), Interrupt> {
+        Err(RuntimeError::new(keyword, "Can only yield inside a generator.").into())
+    }
+}
+
+#[cfg(test)]
+mod interpreter_tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc, thread};
+
+    use crate::{parser::Parser, resolver::Resolver, rlox::RLox, scanner::Scanner, statement::Statement, token::Token};
+
+    use super::{Interpreter, RuntimeError, STACK_SIZE};
+
+    // Runs 'source' and returns what it printed, or the runtime error message
+    fn interpret(source: &str) -> Result<String, String> {
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.to_string(), &mut rlox);
+        let tokens: Vec<Token> = scanner.scan_tokens();
+        let mut parser: Parser = Parser::new(tokens, &mut rlox);
+        let statements: Vec<Statement> = parser.parse();
+        let mut resolver: Resolver = Resolver::new(&mut rlox);
+        resolver.resolve(&statements);
+        let locals: HashMap<usize, usize> = resolver.into_locals();
+        assert!(!rlox.had_error, "Syntax error in {}", source);
+
+        let output: Rc<RefCell<Vec<u8>>> = Rc::new(RefCell::new(Vec::new()));
+        let mut interpreter: Interpreter = Interpreter::with_output(output.clone());
+        interpreter.resolve(locals);
+        let result: Result<(), RuntimeError> = interpreter.interpret(&statements);
+
+        let printed: String = String::from_utf8(output.borrow().clone()).unwrap();
+        result.map(|_| printed).map_err(|error| error.message)
+    }
+
+    fn run(source: &str) -> String {
+        interpret(source).expect("Runtime error")
+    }
+
+    fn run_error(source: &str) -> String {
+        interpret(source).expect_err("Expect a runtime error")
+    }
+
+    #[test]
+    fn test_print_arithmetic() {
+        assert!("7\n" == run("print 1 + 2 * 3;"));
+        assert!("2.5\n" == run("print 5 / 2;"));
+    }
+
+    #[test]
+    fn test_modulo_and_exponent() {
+        assert!("1\n-1\n" == run("print 7 % 3; print -7 % 3;"));
+        assert!("1024\n-4\n0.5\n" == run("print 2 ** 10; print -2 ** 2; print 2 ** -1;"));
+        assert!("512\n" == run("print 2 ** 3 ** 2;"));
+        assert!("Operands must be numbers." == run_error("print \"a\" % 2;"));
+    }
+
+    #[test]
+    fn test_integers() {
+        assert!("9007199254740993\n" == run("print 9007199254740992 + 1;"));
+        assert!("3\n2.5\n-2\n" == run("print 6 / 2; print 5 / 2; print -7 / 3.5;"));
+        assert!("true\ntrue\nfalse\n" == run("print 1 == 1.0; print 2 < 2.5; print 3 == 3.5;"));
+        assert!("3.5\n" == run("var x = 1; x += 2.5; print x;"));
+        assert!("a\n" == run("var map = {1: \"a\"}; print map[1.0];"));
+        assert!("Division by zero." == run_error("print 1 / 0;"));
+        assert!("Division by zero." == run_error("print 1 % 0;"));
+        assert!("inf\n" == run("print 1.0 / 0;"));
+    }
+
+    #[test]
+    fn test_integer_division() {
+        // Exact quotients stay integers, so bitwise operators still take them
+        assert!("3\n1\n" == run("print 6 / 2; print (6 / 2) & 1;"));
+        assert!("-4\n" == run("var x = 8; x /= -2; print x;"));
+        // The rest become floats
+        assert!("3.5\n-0.5\n" == run("print 7 / 2; print 1 / -2;"));
+        assert!("Operands must be integers." == run_error("print (7 / 2) & 1;"));
+        assert!("0.5\n" == run("print 2 ** -1;"));
+    }
+
+    #[test]
+    fn test_big_integers() {
+        assert!("9223372036854775808\n-9223372036854775809\n" == run("print 9223372036854775807 + 1; print -9223372036854775807 - 2;"));
+        assert!("18446744073709551616\n" == run("print 2 ** 64;"));
+        assert!("9223372036854775807\n" == run("var big = 9223372036854775807 * 2; print big / 2;"));
+        assert!("true\ntrue\nfalse\n" == run("var big = 2 ** 70; print big > 2 ** 69; print big == 2.0 ** 70; print -big > 0;"));
+        assert!("a\n" == run("var map = {2 ** 70: \"a\"}; print map[1180591620717411303424];"));
+        assert!("Undefined key '9223372036854775807'." == run_error("var map = {2.0 ** 63: \"big\"}; print map[9223372036854775807];"));
+        assert!("false\n" == run("var set = Set(); set.add(2.0 ** 63); print set.contains(9223372036854775807);"));
+        assert!("0.5\n" == run("print 2 ** 64 / 2 ** 65;"));
+        assert!("Division by zero." == run_error("print 2 ** 64 % 0;"));
+        assert!("Exponent is too large." == run_error("print 2 ** 4000000000;"));
+        assert!("Exponent is too large." == run_error("print rational(1, 2) ** 4000000000;"));
+        assert!("1\n-1\n0\n1\n" == run("print 1 ** 10000000000; print (-1) ** 10000000001; print 0 ** 10000000000; print rational(1, 1) ** 4000000000;"));
+        assert!("Division by zero." == run_error("print rational(0, 1) ** -1;"));
+        assert!("true\nfalse\nfalse\n" == run("print 9007199254740993 > 9007199254740992.0; print 9007199254740993 < 9007199254740992.0; print 9007199254740993 == 9007199254740992.0;"));
+        assert!("true\ntrue\nfalse\n" == run("print 2 ** 70 + 1 > 1180591620717411303424.0; print 2.0 ** 70 < 2 ** 70 + 1; print 2 ** 70 < 0.0 / 0;"));
+        assert!("9223372036854775808\n9223372036854775808\n" == run("print (-9223372036854775807 - 1) / -1; var x = -9223372036854775807 - 1; x /= -1; print x;"));
+
+        let source: &str = "
+            fun factorial(n) { if (n <= 1) return 1; return n * factorial(n - 1); }
+            print factorial(30);
+        ";
+        assert!("265252859812191058636308480000000\n" == run(source));
+    }
+
+    #[test]
+    fn test_rationals() {
+        assert!("1/3\n-1/2\n2\n" == run("print rational(1, 3); print rational(2, -4); print rational(6, 3);"));
+        assert!("-1/6\n1\n9/4\n3/2\n" == run("var a = rational(1, 3); print a + rational(-1, 2); print a * 3; print rational(2, 3) ** -2; print rational(7, 2) % 2;"));
+        assert!("true\nfalse\n" == run("print rational(1, 10) + rational(2, 10) == rational(3, 10); print 0.1 + 0.2 == 0.3;"));
+        assert!("0.75\ntrue\ntrue\n" == run("print rational(1, 4) + 0.5; print rational(1, 2) == 0.5; print rational(1, 3) < rational(1, 2);"));
+        assert!("0.25\n3\n4\n" == run("var r = rational(3, 12); print r.float(); print rational(9, 12).numerator(); print r.denominator();"));
+        assert!("half\n" == run("var map = {rational(1, 2): \"half\"}; print map[0.5];"));
+        assert!("Division by zero." == run_error("rational(1, 0);"));
+        assert!("Division by zero." == run_error("print rational(1, 2) / 0;"));
+        assert!("Numerator and denominator must be integers." == run_error("rational(1.5, 2);"));
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert!("4\n7\n3\n" == run("print 6 & 5; print 6 | 5; print 6 ^ 5;"));
+        assert!("-6\n" == run("print ~5;"));
+        assert!("40\n-3\n" == run("print 5 << 3; print -5 >> 1;"));
+        assert!("true\n" == run("print 6 & 1 == 0;"));
+    }
+
+    #[test]
+    fn test_bitwise_operators_need_integers() {
+        assert!("Operands must be integers." == run_error("print 1.5 & 1;"));
+        assert!("Operands must be integers." == run_error("print 1 | \"1\";"));
+        assert!("Operand must be an integer." == run_error("print ~0.5;"));
+        assert!("Shift amount must be between 0 and 63." == run_error("print 1 << 64;"));
+        assert!("Shift amount must be between 0 and 63." == run_error("print 1 >> -1;"));
+    }
+
+    #[test]
+    fn test_print_string_concatenation() {
+        assert!("foobar\n" == run("print \"foo\" + \"bar\";"));
+    }
+
+    #[test]
+    fn test_print_literals() {
+        assert!("true\nnil\n" == run("print !false; print nil;"));
+    }
+
+    #[test]
+    fn test_conditional_expression() {
+        assert!("yes\n" == run("print true ? \"yes\" : \"no\";"));
+        assert!("no\n" == run("print nil ? \"yes\" : \"no\";"));
+        assert!("2\n" == run("print false ? 1 : true ? 2 : 3;"));
+    }
+
+    #[test]
+    fn test_conditional_only_evaluates_taken_branch() {
+        assert!("1\n" == run("print true ? 1 : -\"not a number\";"));
+    }
+
+    #[test]
+    fn test_comma_expression() {
+        assert!("3\n" == run("print (1, 2, 3);"));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        assert!("Hello world!\n" == run("print \"Hello ${\"world\"}!\";"));
+        assert!("1 + 2 = 3\n" == run("print \"1 + 2 = ${1 + 2}\";"));
+    }
+
+    #[test]
+    fn test_string_interpolation_stringifies_like_print() {
+        assert!("nil true 1.5\n" == run("print \"${nil} ${!false} ${1.5}\";"));
+    }
+
+    #[test]
+    fn test_nested_string_interpolation() {
+        assert!("a b c\n" == run("print \"a ${\"b ${\"c\"}\"}\";"));
+    }
+
+    #[test]
+    fn test_escaped_interpolation() {
+        assert!("${x}\n" == run("print \"\\${x}\";"));
+    }
+
+    #[test]
+    fn test_variables_and_scopes() {
+        let source: &str = "
+            var a = \"global a\";
+            var b = \"global b\";
+            {
+                var a = \"outer a\";
+                {
+                    var a = \"inner a\";
+                    print a;
+                    print b;
+                }
+                print a;
+            }
+            print a;
+        ";
+        assert!("inner a\nglobal b\nouter a\nglobal a\n" == run(source));
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        assert!("Undefined variable 'missing'." == run_error("print missing;"));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert!("yes\n" == run("if (1 < 2) print \"yes\"; else print \"no\";"));
+        assert!("0\n1\n2\n" == run("var i = 0; while (i < 3) { print i; i = i + 1; }"));
+        assert!("0\n1\n1\n2\n" == run("var a = 0; var temp; for (var b = 1; a < 3; b = temp + b) { print a; temp = a; a = b; }"));
+        assert!("a\nnil\n" == run("print nil or \"a\"; print nil and \"b\";"));
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        assert!("0\n1\n" == run("var i = 0; while (true) { if (i == 2) break; print i; i++; }"));
+        assert!("0\n1\n3\n" == run("for (var i = 0; i < 4; i++) { if (i == 2) continue; print i; }"));
+        assert!("0\n1\n0\n" == run("for (var i = 0; i < 2; i++) { for (var j = 0; j < 5; j++) { if (j == 2 - i) break; print j; } }"));
+    }
+
+    #[test]
+    fn test_closures_bind_to_resolved_scope() {
+        let source: &str = "
+            var a = \"global\";
+            {
+                fun showA() {
+                    print a;
+                }
+                showA();
+                var a = \"block\";
+                showA();
+            }
+        ";
+        assert!("global\nglobal\n" == run(source));
+    }
+
+    #[test]
+    fn test_functions_and_closures() {
+        let source: &str = "
+            fun makeCounter() {
+                var count = 0;
+                fun increment() {
+                    count = count + 1;
+                    return count;
+                }
+                return increment;
+            }
+            var counter = makeCounter();
+            counter();
+            print counter();
+        ";
+        assert!("2\n" == run(source));
+        assert!("Expected 2 arguments but got 1." == run_error("fun f(a, b) {} f(1);"));
+        assert!("Can only call functions and classes." == run_error("\"not a function\"();"));
+    }
+
+    #[test]
+    fn test_classes() {
+        let source: &str = "
+            class Point {
+                init(x, y) {
+                    this.x = x;
+                    this.y = y;
+                }
+                sum() {
+                    return this.x + this.y;
+                }
+            }
+            var point = Point(1, 2);
+            print point.sum();
+            print point;
+        ";
+        assert!("3\nPoint instance\n" == run(source));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let source: &str = "
+            class A {
+                name() { return \"A\"; }
+            }
+            class B < A {
+                name() { return \"B after \" + super.name(); }
+            }
+            print B().name();
+        ";
+        assert!("B after A\n" == run(source));
+        assert!("Superclass must be a class." == run_error("var NotAClass = 1; class B < NotAClass {}"));
+    }
+
+    #[test]
+    fn test_static_methods() {
+        let source: &str = "
+            class Math {
+                class square(n) { return n * n; }
+                class cube(n) { return n * Math.square(n); }
+            }
+            class Geometry < Math {}
+            print Math.square(3);
+            print Geometry.cube(2);
+            print Math.square;
+        ";
+        assert!("9\n8\n<fn square>\n" == run(source));
+        assert!("Undefined property 'square'." == run_error("class Math { class square(n) { return n * n; } } Math().square(2);"));
+        assert!("Undefined property 'missing'." == run_error("class Math {} Math.missing();"));
+    }
+
+    #[test]
+    fn test_getters_and_setters() {
+        let source: &str = "
+            class Circle {
+                init(radius) { this.radius = radius; }
+                diameter { return this.radius * 2; }
+                set diameter(value) { this.radius = value / 2; }
+            }
+            class Wheel < Circle {}
+            var circle = Wheel(2);
+            print circle.diameter;
+            circle.diameter = 10;
+            print circle.radius;
+            circle.diameter += 2;
+            print circle.radius;
+            print circle.diameter = 8;
+        ";
+        assert!("4\n5\n6\n8\n" == run(source));
+
+        let source: &str = "
+            class A { x { return 1; } }
+            var a = A();
+            a.x = 2;
+        ";
+        assert!("Property 'x' has a getter but no setter." == run_error(source));
+        assert!("Property 'x' has a getter but no setter." == run_error("class A { x { return 1; } } A().x += 1;"));
+    }
+
+    #[test]
+    fn test_traits() {
+        let source: &str = "
+            trait Named {
+                describe() { return \"I am \" + this.name(); }
+                name() { return \"nobody\"; }
+            }
+            trait Loud {
+                shout() { return this.describe() + \"!\"; }
+            }
+            trait Greeter with Named, Loud {}
+            class Base {
+                name() { return \"base\"; }
+                describe() { return \"base describe\"; }
+            }
+            class Person < Base with Greeter {
+                init(name) { this.person = name; }
+                name() { return this.person; }
+            }
+            var bob = Person(\"bob\");
+            print bob.describe();
+            print bob.shout();
+            print Greeter;
+        ";
+        assert!("I am bob\nI am bob!\n<trait Greeter>\n" == run(source));
+        assert!("Can only mix in traits." == run_error("class A {} class B with A {}"));
+        assert!("Can only call functions and classes." == run_error("trait A {} A();"));
+    }
+
+    #[test]
+    fn test_trait_conflicts() {
+        let conflicting: &str = "
+            trait A { f() { return \"a\"; } }
+            trait B { f() { return \"b\"; } }
+        ";
+        assert!("Method 'f' is provided by both 'A' and 'B'." == run_error(&format!("{} class C with A, B {{}}", conflicting)));
+        assert!("c\n" == run(&format!("{} class C with A, B {{ f() {{ return \"c\"; }} }} print C().f();", conflicting)));
+
+        // A trait reached through two others is the same method, not a conflict
+        let diamond: &str = "
+            trait Base { f() { return \"base\"; } }
+            trait Left with Base {}
+            trait Right with Base {}
+            class C with Left, Right {}
+            print C().f();
+        ";
+        assert!("base\n" == run(diamond));
+    }
+
+    #[test]
+    fn test_generators() {
+        let source: &str = "
+            fun count(n) {
+                var i = 0;
+                while (i < n) {
+                    yield i;
+                    i++;
+                }
+            }
+            var numbers = count(2);
+            print numbers;
+            print numbers.next();
+            print numbers.next();
+            print numbers.next();
+            print numbers.next();
+            for (n in count(3)) print n;
+        ";
+        assert!("<generator count>\n0\n1\nnil\nnil\n0\n1\n2\n" == run(source));
+    }
+
+    #[test]
+    fn test_generators_are_lazy() {
+        let source: &str = "
+            fun naturals() {
+                print \"started\";
+                for (var n = 1; ; n++) yield n;
+            }
+            var all = naturals();
+            print \"created\";
+            for (n in all) {
+                if (n > 3) break;
+                print n;
+            }
+            print all.next();
+        ";
+        assert!("created\nstarted\n1\n2\n3\n5\n" == run(source));
+    }
+
+    #[test]
+    fn test_generator_control_flow() {
+        let source: &str = "
+            fun flow(items) {
+                for (item in items) {
+                    match (item) {
+                        [a, b] => { yield a; yield b; }
+                        0 => continue;
+                        nil => break;
+                        x if x > 100 => return;
+                        x => if (x % 2 == 0) yield \"even\"; else { var odd = x; yield odd; }
+                    }
+                }
+                yield \"done\";
+            }
+            var all = [];
+            for (x in flow([[1, 2], 0, 3, 4, nil, 5])) all.push(x);
+            print all;
+            all = [];
+            for (x in flow([1, 200, 3])) all.push(x);
+            print all;
+        ";
+        assert!("[1, 2, 3, even, done]\n[1]\n" == run(source));
+    }
+
+    #[test]
+    fn test_generator_try_catch_finally() {
+        let source: &str = "
+            fun guarded() {
+                try {
+                    yield 1;
+                    throw \"oops\";
+                } catch (e) {
+                    yield \"caught \" + e;
+                } finally {
+                    print \"cleanup\";
+                }
+                try {
+                    yield 2;
+                    throw \"escaped\";
+                } finally {
+                    yield \"finally\";
+                }
+            }
+            var g = guarded();
+            print g.next();
+            print g.next();
+            print g.next();
+            print g.next();
+            try {
+                g.next();
+            } catch (e) {
+                print \"error \" + e;
+            }
+            print g.next();
+        ";
+        assert!("1\ncaught oops\ncleanup\n2\nfinally\nerror escaped\nnil\n" == run(source));
+
+        let exits: &str = "
+            fun exits() {
+                while (true) {
+                    try {
+                        yield 1;
+                        break;
+                    } finally {
+                        print \"left loop\";
+                    }
+                }
+                try {
+                    yield 2;
+                    return;
+                } finally {
+                    print \"left body\";
+                }
+                yield 3;
+            }
+            for (x in exits()) print x;
+        ";
+        assert!("1\nleft loop\n2\nleft body\n" == run(exits));
+    }
+
+    #[test]
+    fn test_generator_methods_and_closures() {
+        let source: &str = "
+            class Countdown {
+                init(from) { this.from = from; }
+                iter() {
+                    for (n in 0..this.from) yield this.from - n;
+                }
+            }
+            var all = [];
+            for (n in Countdown(3)) all.push(n);
+            print all;
+            fun values() {
+                var seen = 0;
+                yield () => seen;
+                seen = 10;
+                yield nil;
+            }
+            var g = values();
+            var peek = g.next();
+            print g.next();
+            print peek();
+        ";
+        assert!("[3, 2, 1]\nnil\n10\n" == run(source));
+    }
+
+    #[test]
+    fn test_generator_errors() {
+        assert!("Generator is already running." == run_error("fun f() { yield g.next(); } var g = f(); g.next();"));
+        assert!("Operands must be numbers." == run_error("fun f() { yield 1; yield 1 - nil; } for (x in f()) {}"));
+        assert!("nil\n" == run("fun f() { yield 1; yield 1 - nil; } var g = f(); g.next(); try { g.next(); } catch (e) {} print g.next();"));
+    }
+
+    #[test]
+    fn test_fibers_take_turns() {
+        let source: &str = "
+            fun task(name) {
+                return fun () {
+                    for (i in 0..2) {
+                        print name;
+                        print i;
+                        Fiber.yield();
+                    }
+                };
+            }
+            Fiber.spawn(task(\"a\"));
+            Fiber.spawn(task(\"b\"));
+            Fiber.run();
+            print \"done\";
+        ";
+        assert!("a\n0\nb\n0\na\n1\nb\n1\ndone\n" == run(source));
+    }
+
+    #[test]
+    fn test_fibers_give_way_from_any_depth() {
+        let source: &str = "
+            fun step(n) { print n; Fiber.yield(); }
+            Fiber.spawn(fun () { step(\"a\"); step(\"c\"); });
+            Fiber.spawn(fun () { for (v in [\"b\", \"d\"]) step(v); });
+            Fiber.run();
+        ";
+        assert!("a\nb\nc\nd\n" == run(source));
+
+        let source: &str = "
+            class Counter {
+                init(name) { this.name = name; }
+                count(n) { return n == 0 ? 0 : 1 + this.next(n); }
+                next(n) { print this.name; Fiber.yield(); return this.count(n - 1); }
+            }
+            Fiber.spawn(fun () { print Counter(\"a\").count(2); });
+            Fiber.spawn(fun () { print Counter(\"b\").count(1) or \"none\"; });
+            Fiber.run();
+        ";
+        assert!("a\nb\na\n1\n2\n" == run(source));
+
+        let source: &str = "
+            var ch = Channel();
+            fun send_it(v) { ch.send(v); }
+            Fiber.spawn(fun () { send_it(1); send_it(2); });
+            Fiber.spawn(fun () { print \"got ${ch.receive()}\"; print ch.receive() + 10; });
+            Fiber.run();
+        ";
+        assert!("got 1\n12\n" == run(source));
+    }
+
+    #[test]
+    fn test_fiber_channels() {
+        let source: &str = "
+            var numbers = Channel();
+            var total = Channel();
+            Fiber.spawn(fun () {
+                var sum = 0;
+                while (true) {
+                    var n = numbers.receive();
+                    if (n == nil) {
+                        total.send(sum);
+                        return;
+                    }
+                    sum += n;
+                }
+            });
+            Fiber.spawn(fun () {
+                for (i in 1..4) numbers.send(i);
+                numbers.send(nil);
+                print total.receive();
+            });
+            Fiber.run();
+        ";
+        assert!("6\n" == run(source));
+    }
+
+    #[test]
+    fn test_fiber_resume() {
+        let source: &str = "
+            fun steps() { print 1; Fiber.yield(); print 2; }
+            var fiber = Fiber.spawn(steps);
+            fiber.resume();
+            print fiber.done();
+            fiber.resume();
+            print fiber.done();
+            fiber.resume();
+            print fiber;
+        ";
+        assert!("1\nfalse\n2\ntrue\n<fiber steps>\n" == run(source));
+    }
+
+    #[test]
+    fn test_fiber_deadlock() {
+        let source: &str = "
+            var channel = Channel();
+            fun reader() { var value = channel.receive(); }
+            fun writer() { Channel().send(1); }
+            Fiber.spawn(reader);
+            Fiber.spawn(writer);
+            Fiber.run();
+        ";
+        assert!("Deadlock, all fibers are waiting: fiber 1 (reader) to receive, fiber 2 (writer) to send." == run_error(source));
+    }
+
+    #[test]
+    fn test_fiber_errors() {
+        assert!("Channel is empty, only a fiber can wait to receive." == run_error("Channel().receive();"));
+        assert!("Only a fiber can wait to send." == run_error("Channel().send(1);"));
+        assert!("Can only yield from inside a fiber." == run_error("Fiber.yield();"));
+        assert!("A fiber's function can't take arguments." == run_error("fun f(x) {} Fiber.spawn(f);"));
+        assert!("Can only spawn a fiber from a function." == run_error("Fiber.spawn(1);"));
+        assert!("Can't spawn a fiber from a generator, use 'Fiber.yield()' to give way." == run_error("fun f() { yield; } Fiber.spawn(f);"));
+        assert!("Can't run fibers from inside a fiber." == run_error("Fiber.spawn(fun () { Fiber.run(); }); Fiber.run();"));
+        assert!("Fiber is already running." == run_error("var f = Fiber.spawn(fun () { f.resume(); }); Fiber.run();"));
+        assert!("Operand must be a number.\n" == run("Fiber.spawn(fun () { Fiber.yield(); -nil; }); try { Fiber.run(); } catch (e) { print e.message; }"));
+        assert!("A fiber can't give way from inside a callback, getter or generator." == run_error("Fiber.spawn(fun () { [1].map(fun (v) { Fiber.yield(); }); }); Fiber.run();"));
+        assert!("Stack overflow.\n" == run("fun f() { f(); } Fiber.spawn(fun () { try { f(); } catch (e) { print e.message; } }); Fiber.run();"));
+    }
+
+    #[test]
+    fn test_many_fibers() {
+        let source: &str = "
+            var total = Channel();
+            for (i in 0..40000) Fiber.spawn(fun () { total.send(1); });
+            Fiber.spawn(fun () {
+                var sum = 0;
+                for (i in 0..40000) sum += total.receive();
+                print sum;
+            });
+            Fiber.run();
+        ";
+        assert!("40000\n" == run(source));
+    }
+
+    #[test]
+    fn test_fiber_code_keeps_its_meaning() {
+        let source: &str = "
+            fun id(v) { return v; }
+            fun body() {
+                var list = [id(1), 2];
+                list[id(0)] += id(10);
+                print list;
+                print id(false) and id(1) or id(\"or\");
+                print id(true) ? id(\"then\") : id(\"else\");
+                print {id(\"k\"): id(\"v\")};
+                match (id([1, 2])) {
+                    [a, b] if id(a) > 1 => print \"no\";
+                    [a, b] if id(b) > 1 => print \"yes ${a}\";
+                }
+                for (c in id(\"ab\")) {
+                    if (id(c) == \"b\") break;
+                    print c;
+                }
+                try {
+                    throw id(\"thrown\");
+                } catch (e) {
+                    print e;
+                } finally {
+                    print id(\"finally\");
+                }
+                fun early() {
+                    try { return id(\"returned\"); } finally { print \"left\"; }
+                }
+                print early();
+            }
+            Fiber.spawn(body);
+            Fiber.run();
+        ";
+        assert!("[11, 2]\nor\nthen\n{k: v}\nyes 1\na\nthrown\nfinally\nleft\nreturned\n" == run(source));
+    }
+
+    #[test]
+    fn test_dropped_fibers_are_cancelled() {
+        let source: &str = "
+            var fiber = Fiber.spawn(fun () { print 1; Fiber.yield(); print 2; });
+            fiber.resume();
+            fiber = nil;
+            print 3;
+        ";
+        assert!("1\n3\n" == run(source));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert!("15\n" == run("var a = 10; a += 5; print a;"));
+        assert!("4\n" == run("var a = 10; a -= 6; print a;"));
+        assert!("30\n" == run("var a = 10; a *= 3; print a;"));
+        assert!("2.5\n" == run("var a = 10; a /= 4; print a;"));
+        assert!("1\n" == run("var a = 10; a %= 3; print a;"));
+        assert!("ab\n" == run("var s = \"a\"; s += \"b\"; print s;"));
+        assert!("7\n7\n" == run("var a = 2; print a += 5; print a;"));
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        assert!("1\n2\n" == run("var i = 1; print i++; print i;"));
+        assert!("2\n2\n" == run("var i = 1; print ++i; print i;"));
+        assert!("1\n0\n" == run("var i = 1; print i--; print i;"));
+        assert!("0\n0\n" == run("var i = 1; print --i; print i;"));
+        assert!("0\n1\n2\n" == run("var i = 0; while (i < 3) { print i; i++; }"));
+        assert!("Operands must be numbers." == run_error("var s = \"a\"; s--;"));
+    }
+
+    #[test]
+    fn test_compound_assignment_on_fields() {
+        let source: &str = "
+            class Counter {}
+            var counter = Counter();
+            counter.count = 1;
+            counter.count += 2;
+            print counter.count++;
+            print ++counter.count;
+        ";
+        assert!("3\n5\n" == run(source));
+    }
+
+    #[test]
+    fn test_compound_assignment_evaluates_target_once() {
+        let source: &str = "
+            class Box {}
+            var box = Box();
+            box.value = 0;
+            var calls = 0;
+            fun getBox() {
+                calls++;
+                return box;
+            }
+            getBox().value += 10;
+            getBox().value++;
+            print box.value;
+            print calls;
+        ";
+        assert!("11\n2\n" == run(source));
+    }
+
+    #[test]
+    fn test_list_literals_and_indexing() {
+        assert!("[1, 2, 3]\n[]\n" == run("print [1, 2, 3]; print [];"));
+        assert!("1\n3\n2\n" == run("var list = [1, 2, 3,]; print list[0]; print list[-1]; print list[-2];"));
+        assert!("[1, 5, 3]\n" == run("var list = [1, 2, 3]; list[1] = 5; print list;"));
+        assert!("[2, 4]\n" == run("var list = [1, 3]; list[0] += 1; list[-1]++; print list;"));
+        assert!("[1, [2, 3]]\n" == run("print [1, [2, 3]];"));
+    }
+
+    #[test]
+    fn test_list_index_errors() {
+        assert!("List index out of range." == run_error("print [1, 2][2];"));
+        assert!("List index out of range." == run_error("print [1, 2][-3];"));
+        assert!("List index out of range." == run_error("var list = []; list[0] = 1;"));
+        assert!("List index must be an integer." == run_error("print [1, 2][0.5];"));
+        assert!("Only lists and maps can be indexed." == run_error("var a = 1; print a[0];"));
+    }
+
+    #[test]
+    fn test_lists_have_reference_semantics() {
+        assert!("[1, 2]\n" == run("var a = [1]; var b = a; b.push(2); print a;"));
+        assert!("true\nfalse\n" == run("var a = [1]; var b = a; print a == b; print a == [1];"));
+    }
+
+    #[test]
+    fn test_containers_that_contain_themselves() {
+        assert!("[1, [...]]\n" == run("var l = [1]; l.push(l); print l;"));
+        assert!("{a: {...}, b: [{...}]}\n" == run("var m = {}; m[\"a\"] = m; m[\"b\"] = [m]; print m;"));
+        assert!("[[], []]\n" == run("var e = []; print [e, e];"));
+    }
+
+    #[test]
+    fn test_list_methods() {
+        let source: &str = "
+            var list = [1, 2];
+            list.push(3);
+            print list.len();
+            print list.pop();
+            list.insert(0, 0);
+            list.insert(3, 9);
+            print list;
+            print list.remove(-1);
+            print list.slice(1, 10);
+            print list.slice(-2, -1);
+            print list;
+        ";
+        assert!("3\n3\n[0, 1, 2, 9]\n9\n[1, 2]\n[1]\n[0, 1, 2]\n" == run(source));
+        assert!("Can't pop from an empty list." == run_error("[].pop();"));
+        assert!("List index out of range." == run_error("[1].remove(1);"));
+        assert!("Undefined property 'missing'." == run_error("[].missing();"));
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let source: &str = "
+            var add = (a, b) => a + b;
+            print add(1, 2);
+            var greet = fun (name) { return \"hi \" + name; };
+            print greet(\"bob\");
+            print add;
+            print (() => nil)();
+            fun counter() {
+                var count = 0;
+                return () => count += 1;
+            }
+            var next = counter();
+            next();
+            print next();
+        ";
+        assert!("3\nhi bob\n<fn lambda>\nnil\n2\n" == run(source));
+        assert!("Expected 2 arguments but got 1." == run_error("((a, b) => a)(1);"));
+    }
+
+    #[test]
+    fn test_list_map_and_filter() {
+        let source: &str = "
+            var list = [1, 2, 3, 4];
+            print list.map((x) => x * 10);
+            print list.filter((x) => x % 2 == 0);
+            fun square(x) { return x * x; }
+            print list.map(square).filter(fun (x) { return x > 4; });
+            print list;
+        ";
+        assert!("[10, 20, 30, 40]\n[2, 4]\n[9, 16]\n[1, 2, 3, 4]\n" == run(source));
+        assert!("Can only call functions and classes." == run_error("[1].map(1);"));
+        assert!("Expected 2 arguments but got 1." == run_error("[1].map((a, b) => a);"));
+    }
+
+    #[test]
+    fn test_map_literals_and_indexing() {
+        assert!("{a: 1, b: 2}\n{}\n" == run("print {\"a\": 1, \"b\": 2}; print {};"));
+        assert!("2\n" == run("var map = {\"a\": 1}; map[\"a\"] += 1; print map[\"a\"];"));
+        assert!("{nil: 1, true: 2, x: 3}\n" == run("var map = {nil: 1}; map[true] = 2; map[\"x\"] = 3; print map;"));
+        assert!("Undefined key 'b'." == run_error("print {\"a\": 1}[\"b\"];"));
+    }
+
+    #[test]
+    fn test_map_number_keys_compare_by_value() {
+        assert!("{1: c, 0: z}\n" == run("var map = {1: \"a\", 0: \"z\"}; map[1.0] = \"c\"; map[-0] = \"z\"; print map;"));
+        assert!("true\n" == run("print {2: 1}.has(4 / 2);"));
+    }
+
+    #[test]
+    fn test_map_keys_must_be_hashable() {
+        assert!("Map keys must be strings, numbers, booleans or nil." == run_error("print {[]: 1};"));
+        assert!("Map keys must be strings, numbers, booleans or nil." == run_error("var map = {}; map[{}] = 1;"));
+        assert!("Map keys must be strings, numbers, booleans or nil." == run_error("print {}[0.0 / 0];"));
+    }
+
+    #[test]
+    fn test_map_methods() {
+        let source: &str = "
+            var map = {\"a\": 1, \"b\": 2, \"c\": 3};
+            print map.keys();
+            print map.values();
+            print map.has(\"b\");
+            print map.remove(\"b\");
+            print map.remove(\"b\");
+            print map.has(\"b\");
+            print map.len();
+            map[\"d\"] = 4;
+            print map;
+        ";
+        assert!("[a, b, c]\n[1, 2, 3]\ntrue\n2\nnil\nfalse\n2\n{a: 1, c: 3, d: 4}\n" == run(source));
+    }
+
+    #[test]
+    fn test_set_methods() {
+        let source: &str = "
+            var set = Set();
+            set.add(1);
+            set.add(\"a\");
+            set.add(1.0);
+            print set;
+            print set.len();
+            print set.contains(\"a\");
+            print set.remove(\"a\");
+            print set.remove(\"a\");
+            print set.contains(\"a\");
+            print set.values();
+        ";
+        assert!("Set{1, a}\n2\ntrue\ntrue\nfalse\nfalse\n[1]\n" == run(source));
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let source: &str = "
+            var a = Set();
+            var b = Set();
+            a.add(1); a.add(2); a.add(3);
+            b.add(2); b.add(3); b.add(4);
+            print a.union(b);
+            print a.intersection(b);
+            print a.difference(b);
+            print a;
+        ";
+        assert!("Set{1, 2, 3, 4}\nSet{2, 3}\nSet{1}\nSet{1, 2, 3}\n" == run(source));
+        assert!("Operand must be a set." == run_error("Set().union([]);"));
+        assert!("Set elements must be strings, numbers, booleans or nil." == run_error("Set().add([]);"));
+    }
+
+    #[test]
+    fn test_for_in_over_collections() {
+        assert!("1\n2\n3\n" == run("for (x in [1, 2, 3]) print x;"));
+        assert!("a\nb\n" == run("for (key in {\"a\": 1, \"b\": 2}) print key;"));
+        assert!("h\né\n" == run("for (c in \"hé\") print c;"));
+        assert!("1\n2\n" == run("var set = Set(); set.add(1); set.add(2); set.add(1); for (x in set) print x;"));
+        assert!("Can only iterate over lists, maps, sets, strings, ranges and iterators." == run_error("for (x in 1) print x;"));
+    }
+
+    #[test]
+    fn test_for_in_break_continue_and_closures() {
+        assert!("1\n3\n" == run("for (x in [1, 2, 3, 4]) { if (x == 2) continue; if (x == 4) break; print x; }"));
+        let source: &str = "
+            var printers = [];
+            for (x in [1, 2]) {
+                fun show() { print x; }
+                printers.push(show);
+            }
+            printers[0]();
+            printers[1]();
+        ";
+        assert!("1\n2\n" == run(source));
+    }
+
+    #[test]
+    fn test_for_in_sees_elements_pushed_during_the_loop() {
+        assert!("1\n2\n" == run("var list = [1]; for (x in list) { print x; if (x < 2) list.push(x + 1); }"));
+    }
+
+    #[test]
+    fn test_for_in_iterator_protocol() {
+        let source: &str = "
+            class Countdown {
+                init(from) { this.from = from; }
+                iter() { return CountdownIterator(this.from); }
+            }
+            class CountdownIterator {
+                init(current) { this.current = current; }
+                next() {
+                    if (this.current == 0) return nil;
+                    return this.current--;
+                }
+            }
+            for (n in Countdown(3)) print n;
+        ";
+        assert!("3\n2\n1\n" == run(source));
+        assert!("Undefined property 'iter'." == run_error("class A {} for (x in A()) print x;"));
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert!("0..3\n1..=2\n" == run("print 0..3; print 1..=2;"));
+        assert!("0\n1\n2\n" == run("for (i in 0..3) print i;"));
+        assert!("1\n2\n" == run("for (i in 1..=2) print i;"));
+        assert!(run("for (i in 3..0) print i;").is_empty());
+        assert!("3\n4\n0\n" == run("print (0..3).len(); print (0..=3).len(); print (5..1).len();"));
+        assert!("true\nfalse\ntrue\nfalse\n" == run("print (0..3).contains(2.5); print (0..3).contains(3); print (0..=3).contains(3); print (0..3).contains(\"a\");"));
+        assert!("true\n" == run("print 0..3 == 0..3;"));
+        assert!("Operands must be integers." == run_error("print 0..1.5;"));
+    }
+
+    #[test]
+    fn test_large_range_iterates_lazily() {
+        assert!("100000\n" == run("var count = 0; for (i in 0..100000) { count++; } print count;"));
+    }
+
+    #[test]
+    fn test_list_slicing_with_ranges() {
+        assert!("[1, 2]\n[1, 2, 3]\n[2, 3]\n[]\n[0, 1, 2, 3]\n" == run("var list = [0, 1, 2, 3]; print list[1..3]; print list[1..=-1]; print list[-2..10]; print list[3..1]; print list;"));
+    }
+
+    #[test]
+    fn test_throw_and_catch() {
+        assert!("oops\n" == run("try { throw \"oops\"; print \"unreachable\"; } catch (e) { print e; }"));
+        assert!("1\n" == run("fun f() { throw 1; } try { f(); } catch (e) { print e; }"));
+        assert!("nil\nafter\n" == run("try { throw nil; } catch (e) { print e; } print \"after\";"));
+        assert!("oops" == run_error("throw \"oops\";"));
+    }
+
+    #[test]
+    fn test_runtime_errors_are_catchable() {
+        let source: &str = "
+            try {
+                print -\"a\";
+            } catch (error) {
+                print error.message;
+                print error.line;
+                print error;
+            }
+        ";
+        assert!("Operand must be a number.\n3\nError instance\n" == run(source));
+        assert!("Undefined variable 'x'." == run_error("try { print x; } catch (e) { throw e; }"));
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        // Test threads are too small for the deepest calls, the interpreter needs the stack 'main' gives it
+        let test = thread::Builder::new().stack_size(STACK_SIZE).spawn(|| {
+            assert!("Stack overflow." == run_error("fun f() { f(); } f();"));
+            assert!("Stack overflow.\n" == run("fun f() { f(); } try { f(); } catch (error) { print error.message; }"));
+            assert!("Stack overflow." == run_error("class A { x { return this.x; } } A().x;"));
+        });
+        test.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_user_errors_subclass_error() {
+        let source: &str = "
+            class NotFound < Error {
+                init(name) { this.message = name + \" not found\"; }
+            }
+            try { throw NotFound(\"file\"); } catch (e) { print e.message; }
+        ";
+        assert!("file not found\n" == run(source));
+        assert!("file not found" == run_error("class NotFound < Error { init() { this.message = \"file not found\"; } } throw NotFound();"));
+    }
+
+    #[test]
+    fn test_finally_always_runs() {
+        assert!("try\nfinally\n" == run("try { print \"try\"; } finally { print \"finally\"; }"));
+        assert!("catch\nfinally\n" == run("try { throw 1; } catch (e) { print \"catch\"; } finally { print \"finally\"; }"));
+        assert!("finally\n1\n" == run("fun f() { try { return 1; } finally { print \"finally\"; } } print f();"));
+        assert!("0\nfinally\n" == run("for (i in 0..3) { try { print i; break; } finally { print \"finally\"; } }"));
+        assert!("finally\nfinally\n" == run("for (i in 0..2) { try { continue; } finally { print \"finally\"; } }"));
+        assert!("finally\n" == run("try { try { throw 1; } finally { print \"finally\"; } } catch (e) {}"));
+    }
+
+    #[test]
+    fn test_finally_overrides_the_try_outcome() {
+        assert!("2\n" == run("fun f() { try { return 1; } finally { return 2; } } print f();"));
+        assert!("second" == run_error("try { throw \"first\"; } finally { throw \"second\"; }"));
+        assert!("inner\n" == run("try { try { throw 1; } catch (e) { throw \"inner\"; } } catch (e) { print e; }"));
+    }
+
+    #[test]
+    fn test_match_literals_ranges_and_wildcards() {
+        let source: &str = "
+            fun describe(n) {
+                match (n) {
+                    0 => return \"zero\";
+                    -1 => return \"minus one\";
+                    1..=9 => return \"digit\";
+                    \"ten\" => return \"word\";
+                    nil => return \"nothing\";
+                    _ => return \"other\";
+                }
+            }
+            print describe(0);
+            print describe(-1);
+            print describe(9);
+            print describe(\"ten\");
+            print describe(nil);
+            print describe(10);
+        ";
+        assert!("zero\nminus one\ndigit\nword\nnothing\nother\n" == run(source));
+        assert!(run("match (5) { 1 => print 1; }").is_empty());
+    }
+
+    #[test]
+    fn test_match_bindings_and_guards() {
+        assert!("big 12\n" == run("match (12) { n if n < 10 => print \"small\"; n => print \"big \" + \"${n}\"; }"));
+        assert!("1\n" == run("var x = 1; match (2) { x if false => print x; _ => print x; }"));
+    }
+
+    #[test]
+    fn test_match_list_patterns() {
+        let source: &str = "
+            fun sum(list) {
+                match (list) {
+                    [] => return 0;
+                    [first, ..rest] => return first + sum(rest);
+                }
+            }
+            print sum([1, 2, 3]);
+            match ([1, 2]) {
+                [a] => print \"one\";
+                [a, b, ..] => print a + b;
+            }
+            match ([1, [2, 3]]) {
+                [1, [_, c]] => print c;
+            }
+            match (\"not a list\") {
+                [..] => print \"list\";
+                _ => print \"other\";
+            }
+        ";
+        assert!("6\n3\n3\nother\n" == run(source));
+    }
+
+    #[test]
+    fn test_match_instance_patterns() {
+        let source: &str = "
+            class Shape {}
+            class Point < Shape {
+                init(x, y) { this.x = x; this.y = y; }
+            }
+            fun where(p) {
+                match (p) {
+                    Point { x: 0, y: 0 } => print \"origin\";
+                    Point { x: 0, y } => print \"on y axis at ${y}\";
+                    Shape { missing } => print \"unreachable\";
+                    Shape {} => print \"somewhere\";
+                    _ => print \"not a shape\";
+                }
+            }
+            where(Point(0, 0));
+            where(Point(0, 5));
+            where(Point(1, 1));
+            where(1);
+        ";
+        assert!("origin\non y axis at 5\nsomewhere\nnot a shape\n" == run(source));
+        assert!("Instance pattern must name a class." == run_error("var Point = 1; match (1) { Point {} => print 1; }"));
+    }
+
+    #[test]
+    fn test_compound_assignment_on_undefined_variable() {
+        assert!("Undefined variable 'missing'." == run_error("missing += 1;"));
+    }
+}
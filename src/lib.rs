@@ -1,4 +1,24 @@
-#[cfg(test)]
-mod tests {
-
-}
+pub mod rlox;
+pub mod dialect;
+pub mod token_type;
+pub mod token;
+pub mod object;
+pub mod bigint;
+pub mod rational;
+pub mod scanner;
+pub mod expression;
+pub mod statement;
+pub mod parser;
+pub mod pattern;
+pub mod resolver;
+pub mod interpreter;
+pub mod environment;
+pub mod function;
+pub mod generator;
+pub mod fiber;
+pub mod channel;
+pub mod class;
+pub mod list;
+pub mod map;
+pub mod set;
+pub mod range;
@@ -0,0 +1,135 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    function::NativeFunction,
+    interpreter::{Interpreter, RuntimeError},
+    object::Object,
+    range::Range,
+    token::Token,
+};
+
+pub struct List {
+    pub elements: Vec<Object>,
+}
+
+impl List {
+    pub fn new(elements: Vec<Object>) -> Self {
+        Self { elements }
+    }
+
+    pub fn get(&self, bracket: &Token, index: &Object) -> Result<Object, RuntimeError> {
+        let position: usize = self.position(bracket, index, false)?;
+        Ok(self.elements[position].clone())
+    }
+
+    pub fn set(&mut self, bracket: &Token, index: &Object, value: Object) -> Result<(), RuntimeError> {
+        let position: usize = self.position(bracket, index, false)?;
+        self.elements[position] = value;
+        Ok(())
+    }
+
+    // Elements from 'start' up to 'end', negative bounds count back from the end and
+    // out of range bounds are clamped to the list
+    pub fn slice(&self, start: i64, end: i64) -> List {
+        self.slice_positions(self.normalize(start), self.normalize(end))
+    }
+
+    // list[start..end], an inclusive end is resolved before the one is added so '..=-1' reaches the last element
+    pub fn slice_range(&self, range: &Range) -> List {
+        let end: i64 = if range.inclusive { self.normalize(range.end).saturating_add(1) } else { self.normalize(range.end) };
+        self.slice_positions(self.normalize(range.start), end)
+    }
+
+    pub fn method(list: &Rc<RefCell<List>>, name: &Token) -> Option<NativeFunction> {
+        let list: Rc<RefCell<List>> = list.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "push" => NativeFunction::new("push", 1, Rc::new(move |_, _, mut arguments| {
+                list.borrow_mut().elements.push(arguments.remove(0));
+                Ok(Object::Null)
+            })),
+            "pop" => NativeFunction::new("pop", 0, Rc::new(move |_, paren, _| {
+                list.borrow_mut().elements.pop().ok_or_else(|| RuntimeError::new(paren, "Can't pop from an empty list."))
+            })),
+            "len" => NativeFunction::new("len", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Integer(list.borrow().elements.len() as i64))
+            })),
+            "insert" => NativeFunction::new("insert", 2, Rc::new(move |_, paren, mut arguments| {
+                let value: Object = arguments.pop().unwrap_or(Object::Null);
+                // Inserting at the length appends
+                let position: usize = list.borrow().position(paren, &arguments[0], true)?;
+                list.borrow_mut().elements.insert(position, value);
+                Ok(Object::Null)
+            })),
+            "remove" => NativeFunction::new("remove", 1, Rc::new(move |_, paren, arguments| {
+                let position: usize = list.borrow().position(paren, &arguments[0], false)?;
+                Ok(list.borrow_mut().elements.remove(position))
+            })),
+            "slice" => NativeFunction::new("slice", 2, Rc::new(move |_, paren, arguments| {
+                let start: i64 = Self::integer_index(paren, &arguments[0])?;
+                let end: i64 = Self::integer_index(paren, &arguments[1])?;
+                Ok(Object::List(Rc::new(RefCell::new(list.borrow().slice(start, end)))))
+            })),
+            // The callback runs on a snapshot, so it can change the list without affecting the result
+            "map" => NativeFunction::new("map", 1, Rc::new(move |interpreter, paren, mut arguments| {
+                let callback: Object = arguments.remove(0);
+                let elements: Vec<Object> = list.borrow().elements.clone();
+
+                let mut mapped: Vec<Object> = Vec::with_capacity(elements.len());
+                for element in elements {
+                    mapped.push(interpreter.call(callback.clone(), paren, vec![element])?);
+                }
+                Ok(Object::List(Rc::new(RefCell::new(List::new(mapped)))))
+            })),
+            "filter" => NativeFunction::new("filter", 1, Rc::new(move |interpreter, paren, mut arguments| {
+                let callback: Object = arguments.remove(0);
+                let elements: Vec<Object> = list.borrow().elements.clone();
+
+                let mut kept: Vec<Object> = Vec::new();
+                for element in elements {
+                    if Interpreter::is_truthy(&interpreter.call(callback.clone(), paren, vec![element.clone()])?) {
+                        kept.push(element);
+                    }
+                }
+                Ok(Object::List(Rc::new(RefCell::new(List::new(kept)))))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+
+    // The position of an element that must exist, or of the end when 'allow_end' is set
+    fn position(&self, token: &Token, index: &Object, allow_end: bool) -> Result<usize, RuntimeError> {
+        let length: i64 = self.elements.len() as i64;
+        let position: i64 = self.normalize(Self::integer_index(token, index)?);
+
+        let last: i64 = if allow_end { length } else { length - 1 };
+        if position < 0 || position > last {
+            return Err(RuntimeError::new(token, "List index out of range."));
+        }
+        Ok(position as usize)
+    }
+
+    // Negative indices count back from the end, '-1' is the last element
+    fn normalize(&self, index: i64) -> i64 {
+        if index < 0 { index + self.elements.len() as i64 } else { index }
+    }
+
+    fn slice_positions(&self, start: i64, end: i64) -> List {
+        let length: i64 = self.elements.len() as i64;
+        let start: usize = start.clamp(0, length) as usize;
+        let end: usize = end.clamp(0, length).max(start as i64) as usize;
+        List::new(self.elements[start..end].to_vec())
+    }
+
+    fn integer_index(token: &Token, index: &Object) -> Result<i64, RuntimeError> {
+        index.as_integer().ok_or_else(|| RuntimeError::new(token, "List index must be an integer."))
+    }
+}
+
+// A list can contain itself, so only the length is printed
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<list of {}>", self.elements.len())
+    }
+}
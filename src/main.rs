@@ -1,29 +1,35 @@
-#![allow(warnings, dead_code)]
+use std::thread;
 
 use clap::{command, Arg, ArgMatches, Command};
 
-mod rlox;
-mod token_type;
-mod token;
-mod object;
-mod scanner;
-mod expression;
-mod parser;
-
-use rlox::RLox;
+use rlox::{dialect::Dialect, interpreter::STACK_SIZE, rlox::RLox};
 
 fn main() {
     let command: Command = parse_args();
     let matches: ArgMatches = command.get_matches();
-    let mut rlox: rlox::RLox = RLox::new();
+    let dialect: Dialect = matches.get_one::<String>("dialect").unwrap().parse().unwrap();
+
+    // The main thread's stack is too small for the deepest calls the interpreter allows
+    let interpreter = thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+        let mut rlox: RLox = RLox::with_dialect(dialect);
 
-    if let Some(source_path) = matches.get_one("source_path") {
-        rlox.run_file(source_path);
-    } else {
-        rlox.run_prompt();
-    }
+        if let Some(source_path) = matches.get_one("source_path") {
+            rlox.run_file(source_path);
+        } else {
+            rlox.run_prompt();
+        }
+    });
+    interpreter.unwrap().join().unwrap();
 }
 
 fn parse_args() -> Command {
-    command!().arg(Arg::new("source_path").id("source_path").required(false))
+    command!()
+        .arg(Arg::new("source_path").id("source_path").required(false))
+        .arg(
+            Arg::new("dialect")
+                .long("dialect")
+                .help("Which keyword spellings and language extensions are accepted")
+                .value_parser(Dialect::NAMES)
+                .default_value(Dialect::default().name()),
+        )
 }
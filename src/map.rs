@@ -0,0 +1,110 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    function::NativeFunction,
+    interpreter::RuntimeError,
+    list::List,
+    object::{HashKey, Object},
+    token::Token,
+};
+
+// Entries keep their insertion order, so printing and keys() are predictable
+#[derive(Default)]
+pub struct Map {
+    entries: Vec<(Object, Object)>,
+    indices: HashMap<HashKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[(Object, Object)] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, token: &Token, key: &Object) -> Result<Object, RuntimeError> {
+        match self.indices.get(&Self::hash_key(token, key)?) {
+            Some(index) => Ok(self.entries[*index].1.clone()),
+            None => Err(RuntimeError::new(token, &format!("Undefined key '{}'.", key))),
+        }
+    }
+
+    // An existing entry keeps its original key, so '1.0' overwrites the value stored under '1'
+    pub fn set(&mut self, token: &Token, key: Object, value: Object) -> Result<(), RuntimeError> {
+        let hash_key: HashKey = Self::hash_key(token, &key)?;
+        match self.indices.get(&hash_key) {
+            Some(index) => self.entries[*index].1 = value,
+            None => {
+                self.indices.insert(hash_key, self.entries.len());
+                self.entries.push((key, value));
+            },
+        }
+        Ok(())
+    }
+
+    pub fn has(&self, token: &Token, key: &Object) -> Result<bool, RuntimeError> {
+        Ok(self.indices.contains_key(&Self::hash_key(token, key)?))
+    }
+
+    // The removed value, or nil if there was no such key
+    pub fn remove(&mut self, token: &Token, key: &Object) -> Result<Object, RuntimeError> {
+        let Some(index) = self.indices.remove(&Self::hash_key(token, key)?) else {
+            return Ok(Object::Null);
+        };
+
+        let (_, value) = self.entries.remove(index);
+        for later in self.indices.values_mut() {
+            if *later > index {
+                *later -= 1;
+            }
+        }
+        Ok(value)
+    }
+
+    pub fn method(map: &Rc<RefCell<Map>>, name: &Token) -> Option<NativeFunction> {
+        let map: Rc<RefCell<Map>> = map.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "keys" => NativeFunction::new("keys", 0, Rc::new(move |_, _, _| {
+                let keys: Vec<Object> = map.borrow().entries.iter().map(|(key, _)| key.clone()).collect();
+                Ok(Object::List(Rc::new(RefCell::new(List::new(keys)))))
+            })),
+            "values" => NativeFunction::new("values", 0, Rc::new(move |_, _, _| {
+                let values: Vec<Object> = map.borrow().entries.iter().map(|(_, value)| value.clone()).collect();
+                Ok(Object::List(Rc::new(RefCell::new(List::new(values)))))
+            })),
+            "has" => NativeFunction::new("has", 1, Rc::new(move |_, paren, arguments| {
+                Ok(Object::Boolean(map.borrow().has(paren, &arguments[0])?))
+            })),
+            "remove" => NativeFunction::new("remove", 1, Rc::new(move |_, paren, arguments| {
+                map.borrow_mut().remove(paren, &arguments[0])
+            })),
+            "len" => NativeFunction::new("len", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Integer(map.borrow().len() as i64))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+
+    fn hash_key(token: &Token, key: &Object) -> Result<HashKey, RuntimeError> {
+        key.hash_key().ok_or_else(|| RuntimeError::new(token, "Map keys must be strings, numbers, booleans or nil."))
+    }
+}
+
+// A map can contain itself, so only the length is printed
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<map of {}>", self.entries.len())
+    }
+}
@@ -1,7 +1,224 @@
-#[derive(Debug, Clone, PartialEq)]
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{bigint::BigInt, channel::Channel, class::{Class, Instance}, fiber::Fiber, function::{Function, NativeFunction}, generator::Generator, list::List, map::Map, range::Range, rational::Rational, set::Set};
+
+#[derive(Debug, Clone)]
 pub enum Object {
     String(String),
     Number(f64),
+    // Integer literals in the extended dialect, arithmetic on two of them stays exact
+    Integer(i64),
+    // What integers promote to when they overflow, always outside the i64 range
+    BigInt(BigInt),
+    // An exact fraction from 'rational(n, d)'
+    Rational(Rational),
     Boolean(bool),
     Null,
+    Function(Rc<Function>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<Class>),
+    // A trait is kept as a class without a superclass, it can be mixed in but not called
+    Trait(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    List(Rc<RefCell<List>>),
+    Map(Rc<RefCell<Map>>),
+    Set(Rc<RefCell<Set>>),
+    Range(Range),
+    Generator(Rc<RefCell<Generator>>),
+    Fiber(Rc<Fiber>),
+    Channel(Rc<RefCell<Channel>>),
+}
+
+// The values that can be map keys, numbers are keyed by value so '1' and '1.0' are the same key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    String(String),
+    Number(u64),
+    // Integers too large for a float to hold exactly, no float can equal them
+    Integer(i64),
+    BigInt(BigInt),
+    // Fractions a float can't hold exactly
+    Rational(Rational),
+    Boolean(bool),
+    Null,
+}
+
+impl Object {
+    // None for values that can't be keys, NaN is left out because it never equals itself
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::String(string) => Some(HashKey::String(string.clone())),
+            // -0.0 == 0.0, so both get the bits of 0.0
+            Object::Number(number) if *number == 0.0 => Some(HashKey::Number(0.0_f64.to_bits())),
+            Object::Number(number) if !number.is_nan() => Some(HashKey::Number(number.to_bits())),
+            // Keyed like the float they equal, so '1' and '1.0' are still one key. The round trip is
+            // checked in i128 as 'as i64' saturates, which would make i64::MAX equal 2 ** 63.
+            Object::Integer(integer) if *integer as f64 as i128 == *integer as i128 => Object::Number(*integer as f64).hash_key(),
+            Object::Integer(integer) => Some(HashKey::Integer(*integer)),
+            Object::BigInt(integer) if BigInt::from_f64(integer.to_f64()).as_ref() == Some(integer) => Object::Number(integer.to_f64()).hash_key(),
+            Object::BigInt(integer) => Some(HashKey::BigInt(integer.clone())),
+            Object::Rational(rational) if rational.is_integer() => Object::from_big_int(rational.numerator().clone()).hash_key(),
+            Object::Rational(rational) if Rational::from_f64(rational.to_f64()).as_ref() == Some(rational) => Object::Number(rational.to_f64()).hash_key(),
+            Object::Rational(rational) => Some(HashKey::Rational(rational.clone())),
+            Object::Boolean(boolean) => Some(HashKey::Boolean(*boolean)),
+            Object::Null => Some(HashKey::Null),
+            _ => None,
+        }
+    }
+
+    // Integers and floats as a float, for arithmetic that mixes the two
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Object::Number(number) => Some(*number),
+            Object::Integer(integer) => Some(*integer as f64),
+            Object::BigInt(integer) => Some(integer.to_f64()),
+            Object::Rational(rational) => Some(rational.to_f64()),
+            _ => None,
+        }
+    }
+
+    // Integers and rationals, the numbers arithmetic can keep exact
+    pub fn as_rational(&self) -> Option<Rational> {
+        match self {
+            Object::Rational(rational) => Some(rational.clone()),
+            _ => self.as_big_int().map(Rational::from),
+        }
+    }
+
+    // Integers of either size
+    pub fn as_big_int(&self) -> Option<BigInt> {
+        match self {
+            Object::Integer(integer) => Some(BigInt::from(*integer)),
+            Object::BigInt(integer) => Some(integer.clone()),
+            _ => None,
+        }
+    }
+
+    // An Integer when the value fits in an i64, so results drop back to the small form
+    pub fn from_big_int(integer: BigInt) -> Object {
+        match integer.to_i64() {
+            Some(integer) => Object::Integer(integer),
+            None => Object::BigInt(integer),
+        }
+    }
+
+    // Integers, and whole floats that fit in an i64
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Object::Integer(integer) => Some(*integer),
+            Object::Number(number) if number.fract() == 0.0 && *number >= i64::MIN as f64 && *number < i64::MAX as f64 => Some(*number as i64),
+            _ => None,
+        }
+    }
+}
+
+// Values compare by value, everything living on the heap compares by identity
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::String(left), Object::String(right)) => left == right,
+            (Object::Number(left), Object::Number(right)) => left == right,
+            (Object::Integer(left), Object::Integer(right)) => left == right,
+            // Equal when the float is exactly the integer
+            (Object::Integer(integer), Object::Number(number)) | (Object::Number(number), Object::Integer(integer)) => {
+                Object::Number(*number).as_integer() == Some(*integer)
+            },
+            (Object::BigInt(left), Object::BigInt(right)) => left == right,
+            (Object::BigInt(integer), Object::Number(number)) | (Object::Number(number), Object::BigInt(integer)) => {
+                BigInt::from_f64(*number).as_ref() == Some(integer)
+            },
+            (Object::Rational(rational), Object::Number(number)) | (Object::Number(number), Object::Rational(rational)) => {
+                Rational::from_f64(*number).as_ref() == Some(rational)
+            },
+            (Object::Rational(_), _) | (_, Object::Rational(_)) => {
+                self.as_rational().is_some() && self.as_rational() == other.as_rational()
+            },
+            (Object::Boolean(left), Object::Boolean(right)) => left == right,
+            (Object::Null, Object::Null) => true,
+            (Object::Function(left), Object::Function(right)) => Rc::ptr_eq(left, right),
+            (Object::NativeFunction(left), Object::NativeFunction(right)) => Rc::ptr_eq(left, right),
+            (Object::Class(left), Object::Class(right)) => Rc::ptr_eq(left, right),
+            (Object::Trait(left), Object::Trait(right)) => Rc::ptr_eq(left, right),
+            (Object::Instance(left), Object::Instance(right)) => Rc::ptr_eq(left, right),
+            (Object::List(left), Object::List(right)) => Rc::ptr_eq(left, right),
+            (Object::Map(left), Object::Map(right)) => Rc::ptr_eq(left, right),
+            (Object::Set(left), Object::Set(right)) => Rc::ptr_eq(left, right),
+            (Object::Range(left), Object::Range(right)) => left == right,
+            (Object::Generator(left), Object::Generator(right)) => Rc::ptr_eq(left, right),
+            (Object::Fiber(left), Object::Fiber(right)) => Rc::ptr_eq(left, right),
+            (Object::Channel(left), Object::Channel(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+thread_local! {
+    // The lists and maps being printed, by address
+    static PRINTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+// Prints a list or map with 'write', or as 'placeholder' where it contains itself, so a cycle
+// doesn't print forever
+fn write_once(container: usize, placeholder: &str, f: &mut fmt::Formatter<'_>, write: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result) -> fmt::Result {
+    if PRINTING.with(|printing| printing.borrow().contains(&container)) {
+        return write!(f, "{}", placeholder);
+    }
+
+    PRINTING.with(|printing| printing.borrow_mut().push(container));
+    let result: fmt::Result = write(f);
+    PRINTING.with(|printing| printing.borrow_mut().pop());
+    result
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Object::String(string) => write!(f, "{}", string),
+            Object::Number(number) => write!(f, "{}", number),
+            Object::Integer(integer) => write!(f, "{}", integer),
+            Object::BigInt(integer) => write!(f, "{}", integer),
+            Object::Rational(rational) => write!(f, "{}", rational),
+            Object::Boolean(boolean) => write!(f, "{}", boolean),
+            Object::Null => write!(f, "nil"),
+            Object::Function(function) => write!(f, "<fn {}>", function.declaration.name.lexeme),
+            Object::NativeFunction(function) => write!(f, "<native fn {}>", function.name),
+            Object::Class(class) => write!(f, "{}", class.name),
+            Object::Trait(class) => write!(f, "<trait {}>", class.name),
+            Object::Instance(instance) => write!(f, "{} instance", instance.borrow().class.name),
+            Object::List(list) => write_once(Rc::as_ptr(list) as usize, "[...]", f, |f| {
+                write!(f, "[")?;
+                for (i, element) in list.borrow().elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }),
+            Object::Map(map) => write_once(Rc::as_ptr(map) as usize, "{...}", f, |f| {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().entries().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }),
+            Object::Range(range) => write!(f, "{}", range),
+            Object::Set(set) => {
+                write!(f, "Set{{")?;
+                for (i, element) in set.borrow().elements().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "}}")
+            },
+            Object::Generator(generator) => write!(f, "{:?}", generator.borrow()),
+            Object::Fiber(fiber) => write!(f, "{:?}", fiber),
+            Object::Channel(channel) => write!(f, "{:?}", channel.borrow()),
+        }
+    }
 }
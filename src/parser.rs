@@ -1,106 +1,1000 @@
-use crate::{expression::Expression, object::Object, token::Token, token_type::TokenType::{self, *}};
+use std::rc::Rc;
 
-pub struct Parser {
+use crate::{expression::{self, Expression}, object::Object, pattern::Pattern, range::Range, rlox::RLox, statement::{CatchClause, FunctionDeclaration, MatchArm, Method, MethodKind, Statement}, token::Token, token_type::TokenType::{self, *}};
+
+// Text of the `///` comments in front of a declaration
+type DocComment = Option<std::string::String>;
+
+// The error has already been reported when this is returned, it only unwinds to a point where parsing can resume
+#[derive(Debug)]
+pub struct ParseError;
+
+pub struct Parser<'a> {
     tokens: Vec<Token>,
-    current: u64
+    current: u64,
+
+    rlox: &'a mut RLox
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Vec<Token>, rlox: &'a mut RLox) -> Self {
+        Self { tokens, current: 0, rlox }
+    }
+
+    // program →  declaration* EOF ;
+    pub fn parse(&mut self) -> Vec<Statement> {
+        let mut statements: Vec<Statement> = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(ParseError) => self.synchronize(),
+            }
+        }
+        statements
+    }
+
+    // declaration →  classDecl | traitDecl | funDecl | varDecl | statement ;
+    fn declaration(&mut self) -> Result<Statement, ParseError> {
+        // Doc comments end up on the keyword that starts the declaration
+        let doc: DocComment = self.peek().doc;
+
+        if self.match_token(Class) {
+            return self.class_declaration(doc);
+        }
+        if self.match_token(Trait) {
+            return self.trait_declaration(doc);
+        }
+        // 'fun (' starts an expression statement with a lambda
+        if self.check(Fun) && !(self.extended() && self.check_next(LeftParen)) {
+            self.advance();
+            let declaration: Rc<FunctionDeclaration> = self.function("function", doc)?;
+            return Ok(Statement::Function { declaration });
+        }
+        if self.match_token(Var) {
+            return self.var_declaration(doc);
+        }
+
+        self.statement()
+    }
+
+    // classDecl →  "class" IDENTIFIER ( "<" IDENTIFIER )? withClause? "{" method* "}" ;
+    fn class_declaration(&mut self, doc: DocComment) -> Result<Statement, ParseError> {
+        let name: Token = self.consume(Identifier, "Expect class name.")?;
+
+        let mut superclass: Option<Expression> = None;
+        if self.match_token(Less) {
+            let superclass_name: Token = self.consume(Identifier, "Expect superclass name.")?;
+            superclass = Some(Expression::Variable { id: expression::next_id(), name: superclass_name });
+        }
+        let traits: Vec<Expression> = self.with_clause()?;
+
+        self.consume(LeftBrace, "Expect '{' before class body.")?;
+        let methods: Vec<Method> = self.methods("class")?;
+        Ok(Statement::Class { name, superclass, traits, methods, doc })
+    }
+
+    // traitDecl →  "trait" IDENTIFIER withClause? "{" method* "}" ;
+    fn trait_declaration(&mut self, doc: DocComment) -> Result<Statement, ParseError> {
+        let name: Token = self.consume(Identifier, "Expect trait name.")?;
+        let traits: Vec<Expression> = self.with_clause()?;
+
+        self.consume(LeftBrace, "Expect '{' before trait body.")?;
+        let methods: Vec<Method> = self.methods("trait")?;
+        Ok(Statement::Trait { name, traits, methods, doc })
+    }
+
+    // withClause →  "with" IDENTIFIER ( "," IDENTIFIER )* ; 'with' is only special here, so it stays a valid name
+    fn with_clause(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut traits: Vec<Expression> = Vec::new();
+        if !(self.extended() && self.check(Identifier) && self.peek().lexeme == "with") {
+            return Ok(traits);
+        }
+        self.advance();
+
+        loop {
+            let name: Token = self.consume(Identifier, "Expect trait name.")?;
+            traits.push(Expression::Variable { id: expression::next_id(), name });
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+        Ok(traits)
+    }
+
+    // The methods of a class or trait body, up to and including the closing '}'
+    fn methods(&mut self, kind: &str) -> Result<Vec<Method>, ParseError> {
+        let mut methods: Vec<Method> = Vec::new();
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let method_doc: DocComment = self.peek().doc;
+            methods.push(self.method(method_doc)?);
+        }
+
+        self.consume(RightBrace, &format!("Expect '}}' after {} body.", kind))?;
+        Ok(methods)
+    }
+
+    // method →  "class"? function | IDENTIFIER block | "set" function ;
+    fn method(&mut self, doc: DocComment) -> Result<Method, ParseError> {
+        if self.extended() {
+            if self.match_token(Class) {
+                return Ok(Method { kind: MethodKind::Static, declaration: self.function("method", doc)? });
+            }
+
+            // A getter has no parameter list at all
+            if self.check(Identifier) && self.check_next(LeftBrace) {
+                let name: Token = self.advance();
+                self.advance();
+                let body: Vec<Statement> = self.block()?;
+                return Ok(Method { kind: MethodKind::Getter, declaration: Rc::new(FunctionDeclaration::new(name, Vec::new(), body, doc)) });
+            }
+
+            // 'set' is only special before a name, 'set(value) {}' is still a plain method
+            if self.check(Identifier) && self.peek().lexeme == "set" && self.check_next(Identifier) {
+                self.advance();
+                let declaration: Rc<FunctionDeclaration> = self.function("setter", doc)?;
+                if declaration.params.len() != 1 {
+                    self.error(declaration.name.clone(), "A setter must have exactly one parameter.");
+                }
+                return Ok(Method { kind: MethodKind::Setter, declaration });
+            }
+        }
+
+        Ok(Method { kind: MethodKind::Instance, declaration: self.function("method", doc)? })
+    }
+
+    // funDecl →  "fun" function ;
+    // function →  IDENTIFIER "(" parameters? ")" block ;
+    // parameters →  IDENTIFIER ( "," IDENTIFIER )* ;
+    fn function(&mut self, kind: &str, doc: DocComment) -> Result<Rc<FunctionDeclaration>, ParseError> {
+        let name: Token = self.consume(Identifier, &format!("Expect {} name.", kind))?;
+        self.consume(LeftParen, &format!("Expect '(' after {} name.", kind))?;
+        let params: Vec<Token> = self.parameters()?;
+
+        self.consume(LeftBrace, &format!("Expect '{{' before {} body.", kind))?;
+        let body: Vec<Statement> = self.block()?;
+
+        Ok(Rc::new(FunctionDeclaration::new(name, params, body, doc)))
+    }
+
+    // parameters →  IDENTIFIER ( "," IDENTIFIER )* ; including the closing ')'
+    fn parameters(&mut self) -> Result<Vec<Token>, ParseError> {
+        let mut params: Vec<Token> = Vec::new();
+        if !self.check(RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let token: Token = self.peek();
+                    self.error(token, "Can't have more than 255 parameters.");
+                }
+                params.push(self.consume(Identifier, "Expect parameter name.")?);
+
+                if !self.match_token(Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(RightParen, "Expect ')' after parameters.")?;
+        Ok(params)
+    }
+
+    // varDecl →  "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self, doc: DocComment) -> Result<Statement, ParseError> {
+        let name: Token = self.consume(Identifier, "Expect variable name.")?;
+
+        let mut initializer: Option<Expression> = None;
+        if self.match_token(Equal) {
+            initializer = Some(self.expression()?);
+        }
+
+        self.consume(Semicolon, "Expect ';' after variable declaration.")?;
+        Ok(Statement::Var { name, initializer, doc })
+    }
+
+    // statement →  exprStmt | breakStmt | continueStmt | forStmt | ifStmt | matchStmt | printStmt
+    //            | returnStmt | throwStmt | tryStmt | whileStmt | yieldStmt | block ;
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.match_token(Break) {
+            let keyword: Token = self.previous();
+            self.consume(Semicolon, "Expect ';' after 'break'.")?;
+            return Ok(Statement::Break { keyword });
+        }
+        if self.match_token(Continue) {
+            let keyword: Token = self.previous();
+            self.consume(Semicolon, "Expect ';' after 'continue'.")?;
+            return Ok(Statement::Continue { keyword });
+        }
+        if self.match_token(For) {
+            return self.for_statement();
+        }
+        if self.match_token(If) {
+            return self.if_statement();
+        }
+        if self.match_token(Match) {
+            return self.match_statement();
+        }
+        if self.match_token(Print) {
+            return self.print_statement();
+        }
+        if self.match_token(Return) {
+            return self.return_statement();
+        }
+        if self.match_token(Throw) {
+            return self.throw_statement();
+        }
+        if self.match_token(Yield) {
+            return self.yield_statement();
+        }
+        if self.match_token(Try) {
+            return self.try_statement();
+        }
+        if self.match_token(While) {
+            return self.while_statement();
+        }
+        if self.match_token(LeftBrace) {
+            return Ok(Statement::Block { statements: self.block()? });
+        }
+        self.expression_statement()
+    }
+
+    // forStmt →  "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement
+    //          | "for" "(" IDENTIFIER "in" expression ")" statement ;
+    fn for_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(LeftParen, "Expect '(' after 'for'.")?;
+
+        if self.check(Identifier) && self.check_next(In) {
+            return self.for_in_statement();
+        }
+
+        let initializer: Option<Statement> = if self.match_token(Semicolon) {
+            None
+        } else if self.match_token(Var) {
+            Some(self.var_declaration(None)?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let mut condition: Option<Expression> = None;
+        if !self.check(Semicolon) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(Semicolon, "Expect ';' after loop condition.")?;
+
+        let mut increment: Option<Expression> = None;
+        if !self.check(RightParen) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(RightParen, "Expect ')' after for clauses.")?;
+
+        // Desugared into a while loop
+        let mut body: Statement = self.statement()?;
+
+        let condition: Expression = condition.unwrap_or(Expression::Literal { value: Object::Boolean(true) });
+        body = Statement::While { condition, body: Box::new(body), increment };
+
+        if let Some(initializer) = initializer {
+            body = Statement::Block { statements: vec![initializer, body] };
+        }
+
+        Ok(body)
+    }
+
+    // Not desugared, the interpreter steps through the iterable itself
+    fn for_in_statement(&mut self) -> Result<Statement, ParseError> {
+        let name: Token = self.advance();
+        let keyword: Token = self.advance();
+        let iterable: Expression = self.expression()?;
+        self.consume(RightParen, "Expect ')' after for-in clause.")?;
+
+        let body: Statement = self.statement()?;
+        Ok(Statement::ForIn { name, keyword, iterable, body: Box::new(body) })
+    }
+
+    // ifStmt →  "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(LeftParen, "Expect '(' after 'if'.")?;
+        let condition: Expression = self.expression()?;
+        self.consume(RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch: Statement = self.statement()?;
+        let mut else_branch: Option<Box<Statement>> = None;
+        if self.match_token(Else) {
+            else_branch = Some(Box::new(self.statement()?));
+        }
+
+        Ok(Statement::If { condition, then_branch: Box::new(then_branch), else_branch })
+    }
+
+    // matchStmt →  "match" "(" expression ")" "{" ( pattern ( "if" expression )? "=>" statement ","? )* "}" ;
+    fn match_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(LeftParen, "Expect '(' after 'match'.")?;
+        let value: Expression = self.expression()?;
+        self.consume(RightParen, "Expect ')' after match value.")?;
+        self.consume(LeftBrace, "Expect '{' before match arms.")?;
+
+        let mut arms: Vec<MatchArm> = Vec::new();
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let pattern: Pattern = self.pattern()?;
+            let guard: Option<Expression> = if self.match_token(If) { Some(self.guard()?) } else { None };
+            let arrow: Token = self.consume(FatArrow, "Expect '=>' after match pattern.")?;
+            let body: Statement = self.statement()?;
+            self.match_token(Comma);
+
+            arms.push(MatchArm { pattern, guard, arrow, body });
+        }
+
+        self.consume(RightBrace, "Expect '}' after match arms.")?;
+        Ok(Statement::Match { value, arms })
+    }
+
+    // In a guard '(x) =>' is a parenthesized condition followed by the arm's arrow, not an arrow function
+    fn guard(&mut self) -> Result<Expression, ParseError> {
+        if self.check(LeftParen) && self.is_arrow_function() {
+            self.advance();
+            let expression: Expression = self.expression()?;
+            self.consume(RightParen, "Expect ')' after expression")?;
+            return Ok(Expression::Grouping { expression: Box::new(expression) });
+        }
+        self.expression()
+    }
+
+    // pattern →  "_" | IDENTIFIER | IDENTIFIER "{" fields? "}" | listPattern
+    //          | literal ( ( ".." | "..=" ) literal )? ;
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.match_token(Identifier) {
+            let name: Token = self.previous();
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+            if self.match_token(LeftBrace) {
+                return self.instance_pattern(name);
+            }
+            return Ok(Pattern::Binding { name });
+        }
+
+        if self.match_token(LeftBracket) {
+            return self.list_pattern();
+        }
+
+        let value: Object = self.literal_pattern()?;
+        if self.match_tokens(vec![DotDot, DotDotEqual]) {
+            let operator: Token = self.previous();
+            let end: Object = self.literal_pattern()?;
+            return match (value.as_integer(), end.as_integer()) {
+                (Some(start), Some(end)) => Ok(Pattern::Range { range: Range::new(start, end, operator.token_type == DotDotEqual) }),
+                _ => Err(self.error(operator, "Range pattern bounds must be integers.")),
+            };
+        }
+
+        Ok(Pattern::Literal { value })
+    }
+
+    // literal →  "-"? NUMBER | STRING | "true" | "false" | "nil" ;
+    fn literal_pattern(&mut self) -> Result<Object, ParseError> {
+        if self.match_token(Minus) {
+            let number: Token = self.consume(Number, "Expect number after '-' in pattern.")?;
+            return match number.literal {
+                Object::Number(value) => Ok(Object::Number(-value)),
+                Object::Integer(value) => Ok(Object::Integer(-value)),
+                Object::BigInt(value) => Ok(Object::from_big_int(value.negate())),
+                literal => Ok(literal),
+            };
+        }
+        if self.match_tokens(vec![Number, String]) { return Ok(self.previous().literal) };
+        if self.match_token(True) { return Ok(Object::Boolean(true)) };
+        if self.match_token(False) { return Ok(Object::Boolean(false)) };
+        if self.match_token(Nil) { return Ok(Object::Null) };
+
+        let token: Token = self.peek();
+        Err(self.error(token, "Expect pattern."))
+    }
+
+    // listPattern →  "[" ( pattern ( "," pattern )* ( "," ".." IDENTIFIER? )? | ".." IDENTIFIER? )? "]" ;
+    fn list_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let mut elements: Vec<Pattern> = Vec::new();
+        let mut rest: Option<Box<Pattern>> = None;
+
+        while !self.check(RightBracket) {
+            if self.match_token(DotDot) {
+                // '..rest' binds the remaining elements, a bare '..' ignores them
+                let binding: Pattern = if self.match_token(Identifier) && self.previous().lexeme != "_" {
+                    Pattern::Binding { name: self.previous() }
+                } else {
+                    Pattern::Wildcard
+                };
+                rest = Some(Box::new(binding));
+                break;
+            }
+
+            elements.push(self.pattern()?);
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightBracket, "Expect ']' after list pattern.")?;
+        Ok(Pattern::List { elements, rest })
     }
 
-    pub fn parse(&mut self) -> Expression {
-        return self.expression();
+    // fields →  IDENTIFIER ( ":" pattern )? ( "," IDENTIFIER ( ":" pattern )? )* ","? ;
+    fn instance_pattern(&mut self, class: Token) -> Result<Pattern, ParseError> {
+        let mut fields: Vec<(Token, Pattern)> = Vec::new();
+
+        while !self.check(RightBrace) {
+            let field: Token = self.consume(Identifier, "Expect field name in instance pattern.")?;
+            // 'Point { x }' is short for 'Point { x: x }'
+            let pattern: Pattern = if self.match_token(Colon) { self.pattern()? } else { Pattern::Binding { name: field.clone() } };
+            fields.push((field, pattern));
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightBrace, "Expect '}' after instance pattern.")?;
+        let class: Expression = Expression::Variable { id: expression::next_id(), name: class };
+        Ok(Pattern::Instance { class, fields })
     }
 
-    // expression →  equality ;
-    fn expression(&mut self) -> Expression {
-        self.equality()
+    // printStmt →  "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Statement, ParseError> {
+        let expression: Expression = self.expression()?;
+        self.consume(Semicolon, "Expect ';' after value.")?;
+        Ok(Statement::Print { expression })
     }
 
+    // returnStmt →  "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword: Token = self.previous();
+
+        let mut value: Option<Expression> = None;
+        if !self.check(Semicolon) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(Semicolon, "Expect ';' after return value.")?;
+        Ok(Statement::Return { keyword, value })
+    }
+
+    // throwStmt →  "throw" expression ";" ;
+    fn throw_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword: Token = self.previous();
+        let value: Expression = self.expression()?;
+        self.consume(Semicolon, "Expect ';' after thrown value.")?;
+        Ok(Statement::Throw { keyword, value })
+    }
+
+    // yieldStmt →  "yield" expression? ";" ;
+    fn yield_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword: Token = self.previous();
+        let value: Option<Expression> = if self.check(Semicolon) { None } else { Some(self.expression()?) };
+        self.consume(Semicolon, "Expect ';' after yield value.")?;
+        Ok(Statement::Yield { keyword, value })
+    }
+
+    // tryStmt →  "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )? ;
+    fn try_statement(&mut self) -> Result<Statement, ParseError> {
+        let keyword: Token = self.previous();
+        self.consume(LeftBrace, "Expect '{' after 'try'.")?;
+        let body: Vec<Statement> = self.block()?;
+
+        let mut catch: Option<CatchClause> = None;
+        if self.match_token(Catch) {
+            self.consume(LeftParen, "Expect '(' after 'catch'.")?;
+            let name: Token = self.consume(Identifier, "Expect error variable name.")?;
+            self.consume(RightParen, "Expect ')' after error variable name.")?;
+            self.consume(LeftBrace, "Expect '{' before catch body.")?;
+            catch = Some(CatchClause { name, body: self.block()? });
+        }
+
+        let mut finally: Option<Vec<Statement>> = None;
+        if self.match_token(Finally) {
+            self.consume(LeftBrace, "Expect '{' after 'finally'.")?;
+            finally = Some(self.block()?);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(keyword, "Expect 'catch' or 'finally' after try block."));
+        }
+
+        Ok(Statement::Try { body, catch, finally })
+    }
+
+    // whileStmt →  "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(LeftParen, "Expect '(' after 'while'.")?;
+        let condition: Expression = self.expression()?;
+        self.consume(RightParen, "Expect ')' after condition.")?;
+        let body: Statement = self.statement()?;
+
+        Ok(Statement::While { condition, body: Box::new(body), increment: None })
+    }
+
+    // block →  "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Statement>, ParseError> {
+        let mut statements: Vec<Statement> = Vec::new();
+
+        while !self.check(RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    // exprStmt →  expression ";" ;
+    fn expression_statement(&mut self) -> Result<Statement, ParseError> {
+        let expression: Expression = self.expression()?;
+        self.consume(Semicolon, "Expect ';' after expression.")?;
+        Ok(Statement::Expression { expression })
+    }
+
+    // expression →  comma ;
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        self.comma()
+    }
+
+    // comma →  assignment ( "," assignment )* ;
+    fn comma(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.assignment()?;
+
+        while self.extended() && self.match_token(Comma) {
+            let operator: Token = self.previous();
+            let right: Expression = self.assignment()?;
+            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // assignment →  ( call "." )? IDENTIFIER ( "=" | "+=" | "-=" | "*=" | "/=" | "%=" ) assignment | conditional ;
+    fn assignment(&mut self) -> Result<Expression, ParseError> {
+        let expression: Expression = self.conditional()?;
+
+        if self.match_tokens(vec![Equal, PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual]) {
+            let operator: Token = self.previous();
+            let value: Expression = self.assignment()?;
+            return Ok(self.assignment_target(expression, operator, value, false));
+        }
+
+        Ok(expression)
+    }
+
+    // conditional →  logic_or ( "?" expression ":" conditional )? ;
+    fn conditional(&mut self) -> Result<Expression, ParseError> {
+        let condition: Expression = self.or()?;
+
+        if self.match_token(Question) {
+            let then_branch: Expression = self.expression()?;
+            self.consume(Colon, "Expect ':' after then branch of conditional expression.")?;
+            // Right-associative, a ? b : c ? d : e groups as a ? b : (c ? d : e)
+            let else_branch: Expression = self.conditional()?;
+            return Ok(Expression::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            });
+        }
+
+        Ok(condition)
+    }
+
+    // logic_or →  logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.and()?;
+
+        while self.match_token(Or) {
+            let operator: Token = self.previous();
+            let right: Expression = self.and()?;
+            expression = Expression::Logical { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // logic_and →  equality ( "and" equality )* ;
+    fn and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.equality()?;
+
+        while self.match_token(And) {
+            let operator: Token = self.previous();
+            let right: Expression = self.equality()?;
+            expression = Expression::Logical { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
     // equality →  comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Expression {
-        let mut expression: Expression = self.comparison();
+    fn equality(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.comparison()?;
 
         while self.match_tokens(vec![BangEqual, EqualEqual]) {
             let operator: Token = self.previous();
-            let right: Expression = self.comparison();
+            let right: Expression = self.comparison()?;
+            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // comparison →  range ( ( ">" | ">=" | "<" | "<=" ) range )* ;
+    fn comparison(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.range()?;
+
+        while self.match_tokens(vec![Greater, GreaterEqual, Less, LessEqual]) {
+            let operator: Token = self.previous();
+            let right: Expression = self.range()?;
+            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // Not associative, 'a..b..c' is an error
+    // range →  bit_or ( ( ".." | "..=" ) bit_or )? ;
+    fn range(&mut self) -> Result<Expression, ParseError> {
+        let expression: Expression = self.bit_or()?;
+
+        if self.match_tokens(vec![DotDot, DotDotEqual]) {
+            let operator: Token = self.previous();
+            let right: Expression = self.bit_or()?;
+            return Ok(Expression::Binary { left: Box::new(expression), right: Box::new(right), operator });
+        }
+
+        Ok(expression)
+    }
+
+    // Bitwise operators bind tighter than comparisons, so 'a & 1 == 0' means '(a & 1) == 0'
+    // bit_or →  bit_xor ( "|" bit_xor )* ;
+    fn bit_or(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.bit_xor()?;
+
+        while self.match_token(Pipe) {
+            let operator: Token = self.previous();
+            let right: Expression = self.bit_xor()?;
+            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // bit_xor →  bit_and ( "^" bit_and )* ;
+    fn bit_xor(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.bit_and()?;
+
+        while self.match_token(Caret) {
+            let operator: Token = self.previous();
+            let right: Expression = self.bit_and()?;
+            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
+        }
+
+        Ok(expression)
+    }
+
+    // bit_and →  shift ( "&" shift )* ;
+    fn bit_and(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.shift()?;
+
+        while self.match_token(Ampersand) {
+            let operator: Token = self.previous();
+            let right: Expression = self.shift()?;
             expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
         }
 
-        expression
+        Ok(expression)
     }
 
-    // comparison →  term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Expression {
-        let mut expression: Expression = self.term();
+    // shift →  term ( ( "<<" | ">>" ) term )* ;
+    fn shift(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.term()?;
 
-        while (self.match_tokens(vec![Greater, GreaterEqual, Less, LessEqual])) {
+        while self.match_tokens(vec![LessLess, GreaterGreater]) {
             let operator: Token = self.previous();
-            let right: Expression = self.term();
+            let right: Expression = self.term()?;
             expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
         }
 
-        expression
+        Ok(expression)
     }
 
     // term →  factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Expression {
-        let mut expression: Expression = self.factor();
+    fn term(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.factor()?;
 
         while self.match_tokens(vec![Minus, Plus]) {
            let operator: Token = self.previous();
-           let right: Expression = self.factor();
+           let right: Expression = self.factor()?;
            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
         }
 
-        expression
+        Ok(expression)
     }
 
-    // factor →  unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Expression {
-        let mut expression: Expression = self.unary();
+    // factor →  unary ( ( "/" | "*" | "%" ) unary )* ;
+    fn factor(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.unary()?;
 
-        while self.match_tokens(vec![Slash, Star]) {
+        while self.match_tokens(vec![Slash, Star, Percent]) {
            let operator: Token = self.previous();
-           let right: Expression = self.unary();
+           let right: Expression = self.unary()?;
            expression = Expression::Binary { left: Box::new(expression), right: Box::new(right), operator }
         }
 
-        expression
+        Ok(expression)
+    }
+
+    // unary →  ( "!" | "-" | "~" ) unary | ( "++" | "--" ) unary | exponent ;
+    fn unary(&mut self) -> Result<Expression, ParseError> {
+        if self.match_tokens(vec![Bang, Minus, Tilde]) {
+            let operator: Token = self.previous();
+            let right: Expression = self.unary()?;
+            return Ok(Expression::Unary { operator, right: Box::new(right) });
+        }
+
+        if self.match_tokens(vec![PlusPlus, MinusMinus]) {
+            let operator: Token = self.previous();
+            let target: Expression = self.unary()?;
+            return Ok(self.assignment_target(target, operator, Expression::Literal { value: Object::Integer(1) }, false));
+        }
+
+        self.exponent()
+    }
+
+    // Right-associative and tighter than a unary minus on its left, '-2 ** 2' is -4 and '2 ** -1' is 0.5
+    // exponent →  postfix ( "**" unary )? ;
+    fn exponent(&mut self) -> Result<Expression, ParseError> {
+        let expression: Expression = self.postfix()?;
+
+        if self.match_token(StarStar) {
+            let operator: Token = self.previous();
+            let right: Expression = self.unary()?;
+            return Ok(Expression::Binary { left: Box::new(expression), right: Box::new(right), operator });
+        }
+
+        Ok(expression)
     }
 
-    // unary →  ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Expression {
-        if self.match_tokens(vec![Bang, Minus]) {
+    // postfix →  call ( "++" | "--" )? ;
+    fn postfix(&mut self) -> Result<Expression, ParseError> {
+        let expression: Expression = self.call()?;
+
+        if self.match_tokens(vec![PlusPlus, MinusMinus]) {
             let operator: Token = self.previous();
-            let right: Expression = self.unary();
-            return Expression::Unary { operator, right: Box::new(right) };
+            return Ok(self.assignment_target(expression, operator, Expression::Literal { value: Object::Integer(1) }, true));
         }
 
-        self.primary()
+        Ok(expression)
     }
 
+    // call →  primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
+    fn call(&mut self) -> Result<Expression, ParseError> {
+        let mut expression: Expression = self.primary()?;
 
-    // primary →  NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
-    fn primary(&mut self) -> Expression {
-        if self.match_token(False) { return Expression::Literal { value: Object::Boolean(false)}};
-        if self.match_token(True) { return Expression::Literal { value: Object::Boolean(true)}};
-        if self.match_token(Null) { return Expression::Literal { value: Object::Null}};
+        loop {
+            if self.match_token(LeftParen) {
+                expression = self.finish_call(expression)?;
+            } else if self.match_token(Dot) {
+                // 'yield' is a keyword, but it still names the method in 'Fiber.yield()'
+                let name: Token = if self.match_token(Yield) {
+                    Token { token_type: Identifier, ..self.previous() }
+                } else {
+                    self.consume(Identifier, "Expect property name after '.'.")?
+                };
+                expression = Expression::Get { object: Box::new(expression), name };
+            } else if self.match_token(LeftBracket) {
+                let index: Expression = self.expression()?;
+                let bracket: Token = self.consume(RightBracket, "Expect ']' after index.")?;
+                expression = Expression::Index { object: Box::new(expression), bracket, index: Box::new(index) };
+            } else {
+                break;
+            }
+        }
 
-        if self.match_tokens(vec![Number, String]) { return Expression::Literal { value: self.previous().literal}};
+        Ok(expression)
+    }
+
+    // arguments →  assignment ( "," assignment )* ;
+    fn finish_call(&mut self, callee: Expression) -> Result<Expression, ParseError> {
+        let mut arguments: Vec<Expression> = Vec::new();
+
+        if !self.check(RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    let token: Token = self.peek();
+                    self.error(token, "Can't have more than 255 arguments.");
+                }
+                arguments.push(self.assignment()?);
+
+                if !self.match_token(Comma) {
+                    break;
+                }
+            }
+        }
+
+        let paren: Token = self.consume(RightParen, "Expect ')' after arguments.")?;
+        Ok(Expression::Call { callee: Box::new(callee), paren, arguments })
+    }
+
+    // primary →  NUMBER | STRING | interpolation | "true" | "false" | "nil" | "this"
+    //          | IDENTIFIER | "(" expression ")" | "super" "." IDENTIFIER | list | map ;
+    fn primary(&mut self) -> Result<Expression, ParseError> {
+        if self.match_token(False) { return Ok(Expression::Literal { value: Object::Boolean(false)})};
+        if self.match_token(True) { return Ok(Expression::Literal { value: Object::Boolean(true)})};
+        if self.match_token(Nil) { return Ok(Expression::Literal { value: Object::Null})};
+
+        // The rest of an interpolated string where the embedded expression should be, as in "${}"
+        if (self.check(String) || self.check(Interpolation)) && self.peek().lexeme.starts_with('}') {
+            let token: Token = self.peek();
+            return Err(self.error(token, "Expect expression inside '${}'."));
+        }
+
+        if self.match_tokens(vec![Number, String]) { return Ok(Expression::Literal { value: self.previous().literal})};
+
+        if self.match_token(Interpolation) { return self.interpolation() };
+
+        if self.match_token(Super) {
+            let keyword: Token = self.previous();
+            self.consume(Dot, "Expect '.' after 'super'.")?;
+            let method: Token = self.consume(Identifier, "Expect superclass method name.")?;
+            return Ok(Expression::Super { id: expression::next_id(), keyword, method });
+        }
+
+        if self.match_token(This) { return Ok(Expression::This { id: expression::next_id(), keyword: self.previous() }) };
+
+        if self.match_token(Identifier) { return Ok(Expression::Variable { id: expression::next_id(), name: self.previous() }) };
+
+        if self.extended() && self.match_token(Fun) { return self.lambda() };
+
+        if self.extended() && self.check(LeftParen) && self.is_arrow_function() { return self.arrow_function() };
 
         if self.match_token(LeftParen) {
-            let expression: Expression = self.expression();
-            self.consume(RightParen, "Expect ')' after expression");
-            return Expression::Grouping { expression: Box::new(expression) }
+            let expression: Expression = self.expression()?;
+            self.consume(RightParen, "Expect ')' after expression")?;
+            return Ok(Expression::Grouping { expression: Box::new(expression) })
         }
 
-        panic!("Expect expression");
+        if self.match_token(LeftBracket) { return self.list() };
+
+        // A '{' starting a statement is a block, so map literals only appear inside expressions
+        if self.extended() && self.match_token(LeftBrace) { return self.map() };
+
+        let token: Token = self.peek();
+        Err(self.error(token, "Expect expression."))
+    }
+
+    // lambda →  "fun" "(" parameters? ")" block ;
+    fn lambda(&mut self) -> Result<Expression, ParseError> {
+        let keyword: Token = self.previous();
+        self.consume(LeftParen, "Expect '(' after 'fun'.")?;
+        let params: Vec<Token> = self.parameters()?;
+
+        self.consume(LeftBrace, "Expect '{' before lambda body.")?;
+        let body: Vec<Statement> = self.block()?;
+
+        Ok(Self::lambda_expression(&keyword, params, body))
+    }
+
+    // arrowFunction →  "(" parameters? ")" "=>" assignment ;
+    fn arrow_function(&mut self) -> Result<Expression, ParseError> {
+        let paren: Token = self.advance();
+        let params: Vec<Token> = self.parameters()?;
+        let arrow: Token = self.consume(FatArrow, "Expect '=>' after parameters.")?;
+
+        // The body is a single expression, so a comma after it still separates call arguments
+        let value: Expression = self.assignment()?;
+        let body: Vec<Statement> = vec![Statement::Return { keyword: arrow, value: Some(value) }];
+
+        Ok(Self::lambda_expression(&paren, params, body))
+    }
+
+    // Lambdas are named 'lambda' so they print as '<fn lambda>'
+    fn lambda_expression(token: &Token, params: Vec<Token>, body: Vec<Statement>) -> Expression {
+        let name: Token = Token::new(Identifier, "lambda".to_string(), Object::Null, token.line);
+        Expression::Function { declaration: Rc::new(FunctionDeclaration::new(name, params, body, None)) }
+    }
+
+    // Whether the '(' at the current token opens the parameters of an arrow function rather than a grouping
+    fn is_arrow_function(&self) -> bool {
+        let mut tokens = self.tokens[self.current as usize + 1..].iter().map(|token| &token.token_type);
+        let mut expect_parameter: bool = true;
+
+        loop {
+            match (tokens.next(), expect_parameter) {
+                (Some(Identifier), true) => expect_parameter = false,
+                (Some(Comma), false) => expect_parameter = true,
+                (Some(RightParen), _) => return tokens.next() == Some(&FatArrow),
+                _ => return false,
+            }
+        }
+    }
+
+    // list →  "[" ( assignment ( "," assignment )* ","? )? "]" ;
+    fn list(&mut self) -> Result<Expression, ParseError> {
+        let mut elements: Vec<Expression> = Vec::new();
+
+        while !self.check(RightBracket) {
+            elements.push(self.assignment()?);
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        self.consume(RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expression::List { elements })
+    }
+
+    // map →  "{" ( assignment ":" assignment ( "," assignment ":" assignment )* ","? )? "}" ;
+    fn map(&mut self) -> Result<Expression, ParseError> {
+        let mut entries: Vec<(Expression, Expression)> = Vec::new();
+
+        while !self.check(RightBrace) {
+            let key: Expression = self.assignment()?;
+            self.consume(Colon, "Expect ':' after map key.")?;
+            let value: Expression = self.assignment()?;
+            entries.push((key, value));
+
+            if !self.match_token(Comma) {
+                break;
+            }
+        }
+
+        let brace: Token = self.consume(RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expression::Map { brace, entries })
+    }
+    // interpolation →  ( INTERPOLATION expression )+ STRING ;
+    fn interpolation(&mut self) -> Result<Expression, ParseError> {
+        let mut parts: Vec<Expression> = vec![Expression::Literal { value: self.previous().literal }];
+
+        loop {
+            parts.push(self.expression()?);
+
+            if self.match_token(Interpolation) {
+                parts.push(Expression::Literal { value: self.previous().literal });
+            } else {
+                let end: Token = self.consume(String, "Expect end of string interpolation.")?;
+                parts.push(Expression::Literal { value: end.literal });
+                break;
+            }
+        }
+
+        Ok(Expression::Interpolation { parts })
+    }
+
+    // Turns 'target = value', 'target += value', '++target' and 'target++' into the node
+    // for the kind of target, variables, fields and elements are the only valid ones
+    fn assignment_target(&mut self, target: Expression, operator: Token, value: Expression, postfix: bool) -> Expression {
+        let compound: bool = operator.token_type != Equal;
+
+        match target {
+            Expression::Variable { id, name } if compound => {
+                Expression::CompoundAssign { id, name, operator, value: Box::new(value), postfix }
+            },
+            Expression::Variable { id, name } => Expression::Assign { id, name, value: Box::new(value) },
+            Expression::Get { object, name } if compound => {
+                Expression::CompoundSet { object, name, operator, value: Box::new(value), postfix }
+            },
+            Expression::Get { object, name } => Expression::Set { object, name, value: Box::new(value) },
+            Expression::Index { object, bracket, index } if compound => {
+                Expression::CompoundSetIndex { object, bracket, index, operator, value: Box::new(value), postfix }
+            },
+            Expression::Index { object, bracket, index } => Expression::SetIndex { object, bracket, index, value: Box::new(value) },
+            _ => {
+                // Reported without unwinding, the parser is not confused about where it is
+                let message: &str = match operator.token_type {
+                    PlusPlus | MinusMinus => "Invalid increment target.",
+                    _ => "Invalid assignment target.",
+                };
+                self.error(operator, message);
+                target
+            },
+        }
     }
 
     // Helpers
+    fn extended(&self) -> bool {
+        self.rlox.dialect.has_extensions()
+    }
+
     fn match_token(&mut self, token_type: TokenType) -> bool {
         if self.check(token_type) {
             self.advance();
@@ -126,6 +1020,14 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
+    // Like 'check', but one token further ahead
+    fn check_next(&mut self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current as usize + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1;
@@ -146,30 +1048,423 @@ impl Parser {
         self.tokens.get((self.current - 1) as usize).unwrap().clone()
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Token {
-        if self.check(token_type.clone()) {
-            return self.advance();
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParseError> {
+        if self.check(token_type) {
+            return Ok(self.advance());
         }
 
-        panic!("Expect {:?}", token_type);
+        let token: Token = self.peek();
+        Err(self.error(token, message))
+    }
+
+    fn error(&mut self, token: Token, message: &str) -> ParseError {
+        // Error tokens were already reported by the scanner
+        if token.token_type != Error {
+            self.rlox.token_error(&token, message);
+        }
+        ParseError
+    }
+
+    // Discards tokens until the start of the next statement
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                Class | Fun | Var | For | If | While | Print | Return | Break | Continue | Throw | Try | Match | Trait | Yield => return,
+                _ => { self.advance(); },
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod parser_tests {
-    use crate::{expression::Expression, token::Token, token_type::TokenType};
+    use crate::{expression::Expression, object::Object, pattern::Pattern, range::Range, rlox::RLox, scanner::Scanner, statement::{MethodKind, Statement}, token::Token, token_type::TokenType};
 
     use super::Parser;
 
+    fn parse(source: &str, rlox: &mut RLox) -> Vec<Statement> {
+        let mut scanner: Scanner = Scanner::new(source.to_string(), rlox);
+        let tokens: Vec<Token> = scanner.scan_tokens();
+        let mut parser: Parser = Parser::new(tokens, rlox);
+        parser.parse()
+    }
 
     #[test]
     fn test_parse_primary_expression() {
         let tokens: Vec<Token> = vec![
             Token::new(TokenType::String, "some string".to_string(), crate::object::Object::String("some string".to_string()), 1),
+            Token::new(TokenType::Semicolon, ";".to_string(), crate::object::Object::Null, 1),
             Token::new(TokenType::Eof, "".to_string(), crate::object::Object::Null, 1)
         ];
-        let mut parser: Parser = Parser::new(tokens);
-        let result: Expression = parser.parse();
+        let mut rlox: RLox = RLox::new();
+        let mut parser: Parser = Parser::new(tokens, &mut rlox);
+        let result: Vec<Statement> = parser.parse();
         println!("[RESULT]: {:?}", result);
     }
+
+    #[test]
+    fn test_parse_conditional_is_right_associative() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("true ? 1 : false ? 2 : 3;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::Conditional { else_branch, .. } } = &result[0] else {
+            panic!("Expect a conditional expression");
+        };
+        assert!(matches!(**else_branch, Expression::Conditional { .. }));
+    }
+
+    #[test]
+    fn test_parse_conditional_missing_colon() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print true ? 1;", &mut rlox);
+
+        assert!(result.is_empty());
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_comma_has_lowest_precedence() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("true ? 1 : 2, 3;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::Binary { left, operator, .. } } = &result[0] else {
+            panic!("Expect a comma expression");
+        };
+        assert!(TokenType::Comma == operator.token_type);
+        assert!(matches!(**left, Expression::Conditional { .. }));
+    }
+
+    #[test]
+    fn test_parse_bitwise_binds_tighter_than_comparison() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("1 & 3 == 1 | 2 << 1;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::Binary { left, operator, right } } = &result[0] else {
+            panic!("Expect a binary expression");
+        };
+        assert!(TokenType::EqualEqual == operator.token_type);
+        assert!(matches!(&**left, Expression::Binary { operator, .. } if operator.token_type == TokenType::Ampersand));
+        assert!(matches!(&**right, Expression::Binary { operator, .. } if operator.token_type == TokenType::Pipe));
+    }
+
+    #[test]
+    fn test_parse_exponent_is_right_associative() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("-2 ** 3 ** 2;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::Unary { right, .. } } = &result[0] else {
+            panic!("Expect the minus to apply to the whole exponent");
+        };
+        let Expression::Binary { right, .. } = &**right else {
+            panic!("Expect an exponent expression");
+        };
+        assert!(matches!(&**right, Expression::Binary { operator, .. } if operator.token_type == TokenType::StarStar));
+    }
+
+    #[test]
+    fn test_parse_list_literal_and_index_assignment() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("[1, 2][0] = [];", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::SetIndex { object, value, .. } } = &result[0] else {
+            panic!("Expect an index assignment");
+        };
+        assert!(matches!(&**object, Expression::List { elements } if elements.len() == 2));
+        assert!(matches!(&**value, Expression::List { elements } if elements.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_map_literal() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print {\"a\": 1, 2: true ? 3 : 4,};", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Print { expression: Expression::Map { entries, .. } } = &result[0] else {
+            panic!("Expect a map literal");
+        };
+        assert!(2 == entries.len());
+        assert!(matches!(entries[1].1, Expression::Conditional { .. }));
+    }
+
+    #[test]
+    fn test_parse_brace_statement_is_a_block() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("{}", &mut rlox);
+
+        assert!(!rlox.had_error);
+        assert!(matches!(result[0], Statement::Block { .. }));
+    }
+
+    #[test]
+    fn test_parse_for_in_loop() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("for (x in [1, 2]) print x;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::ForIn { name, iterable, body, .. } = &result[0] else {
+            panic!("Expect a for-in loop");
+        };
+        assert!("x" == name.lexeme);
+        assert!(matches!(iterable, Expression::List { .. }));
+        assert!(matches!(**body, Statement::Print { .. }));
+    }
+
+    #[test]
+    fn test_parse_range_binds_looser_than_arithmetic() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("0..n + 1;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Expression { expression: Expression::Binary { operator, right, .. } } = &result[0] else {
+            panic!("Expect a range expression");
+        };
+        assert!(TokenType::DotDot == operator.token_type);
+        assert!(matches!(**right, Expression::Binary { .. }));
+    }
+
+    #[test]
+    fn test_parse_range_is_not_associative() {
+        let mut rlox: RLox = RLox::new();
+        parse("0..1..2;", &mut rlox);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_try_statement() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("try { throw 1; } catch (e) { print e; } finally { print 2; } try {} finally {}", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Try { body, catch: Some(catch), finally: Some(_) } = &result[0] else {
+            panic!("Expect a try statement with catch and finally");
+        };
+        assert!(matches!(body[0], Statement::Throw { .. }));
+        assert!("e" == catch.name.lexeme);
+        assert!(matches!(result[1], Statement::Try { catch: None, .. }));
+    }
+
+    #[test]
+    fn test_parse_try_needs_catch_or_finally() {
+        let mut rlox: RLox = RLox::new();
+        parse("try { print 1; }", &mut rlox);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_lambdas() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("fun (a) { print a; }; var f = (a, b) => a + b; (x) => x, 1; print (1);", &mut rlox);
+
+        assert!(!rlox.had_error);
+        assert!(matches!(&result[0], Statement::Expression { expression: Expression::Function { declaration } } if declaration.params.len() == 1));
+        let Statement::Var { initializer: Some(Expression::Function { declaration }), .. } = &result[1] else {
+            panic!("Expect an arrow function");
+        };
+        assert!(2 == declaration.params.len());
+        assert!(matches!(declaration.body[0], Statement::Return { value: Some(Expression::Binary { .. }), .. }));
+        assert!(matches!(&result[2], Statement::Expression { expression: Expression::Binary { left, .. } } if matches!(**left, Expression::Function { .. })));
+        assert!(matches!(&result[3], Statement::Print { expression: Expression::Grouping { .. } }));
+    }
+
+    #[test]
+    fn test_parse_parenthesized_guard_is_not_an_arrow_function() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("match (1) { x if (x) => print x; }", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Match { arms, .. } = &result[0] else {
+            panic!("Expect a match statement");
+        };
+        assert!(matches!(arms[0].guard, Some(Expression::Grouping { .. })));
+    }
+
+    #[test]
+    fn test_parse_yield_makes_a_generator() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("fun g() { if (true) { yield 1; } yield; } fun f() { fun inner() { yield 1; } }", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let (Statement::Function { declaration: generator }, Statement::Function { declaration: function }) = (&result[0], &result[1]) else {
+            panic!("Expect two function declarations");
+        };
+        assert!(generator.generator.is_some());
+        assert!(matches!(generator.body[1], Statement::Yield { value: None, .. }));
+        assert!(function.generator.is_none());
+    }
+
+    #[test]
+    fn test_parse_match_statement() {
+        let source: &str = "
+            match (x) {
+                0 => print 0;
+                -1..=5 if x > 2 => print 1;
+                [first, ..rest] => { print first; },
+                Point { x: 0, y } => { print y; }
+                _ => print nil;
+            }
+        ";
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse(source, &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Match { arms, .. } = &result[0] else {
+            panic!("Expect a match statement");
+        };
+        assert!(5 == arms.len());
+        assert!(matches!(arms[0].pattern, Pattern::Literal { value: Object::Integer(0) }));
+        assert!(matches!(arms[1].pattern, Pattern::Range { range: Range { start: -1, end: 5, inclusive: true } }));
+        assert!(arms[1].guard.is_some());
+        assert!(matches!(&arms[2].pattern, Pattern::List { elements, rest: Some(_) } if elements.len() == 1));
+        assert!(matches!(&arms[3].pattern, Pattern::Instance { fields, .. } if fields.len() == 2));
+        assert!(matches!(arms[4].pattern, Pattern::Wildcard));
+    }
+
+    #[test]
+    fn test_parse_missing_semicolon_is_reported() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print 1", &mut rlox);
+
+        assert!(result.is_empty());
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_recovers_after_error() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print 1; print (2; print 3; 4 +; print 5;", &mut rlox);
+
+        assert!(rlox.had_error);
+        assert!(3 == result.len());
+    }
+
+    #[test]
+    fn test_parse_recovers_around_error_tokens() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print 1; print @; print 2; print \"unterminated", &mut rlox);
+
+        assert!(rlox.had_error);
+        assert!(2 == result.len());
+    }
+
+    #[test]
+    fn test_parse_empty_interpolation() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("print \"a ${}\"; print \"b ${1 + } c\"; print \"${x}\";", &mut rlox);
+
+        assert!(rlox.had_error);
+        assert!(1 == result.len());
+    }
+
+    #[test]
+    fn test_parse_invalid_assignment_target() {
+        let mut rlox: RLox = RLox::new();
+        parse("1 + 2 = 3;", &mut rlox);
+        assert!(rlox.had_error);
+
+        let mut rlox: RLox = RLox::new();
+        parse("true ? a : b = 1;", &mut rlox);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_invalid_increment_target() {
+        let mut rlox: RLox = RLox::new();
+        parse("(a + b)++;", &mut rlox);
+        assert!(rlox.had_error);
+
+        let mut rlox: RLox = RLox::new();
+        parse("++a++;", &mut rlox);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_assignment_in_conditional_branch() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("var x = c ? y = 1 : 2;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Var { initializer: Some(Expression::Conditional { then_branch, .. }), .. } = &result[0] else {
+            panic!("Expect a conditional initializer");
+        };
+        assert!(matches!(**then_branch, Expression::Assign { .. }));
+    }
+
+    #[test]
+    fn test_parse_compound_assignment_on_field() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("a.b.c *= 2; a.b++;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        assert!(matches!(result[0], Statement::Expression { expression: Expression::CompoundSet { postfix: false, .. } }));
+        assert!(matches!(result[1], Statement::Expression { expression: Expression::CompoundSet { postfix: true, .. } }));
+    }
+
+    #[test]
+    fn test_parse_class_method_kinds() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("class A { init() {} class create() {} area { return 1; } set area(value) {} set(value) {} }", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Class { methods, .. } = &result[0] else {
+            panic!("Expect a class declaration");
+        };
+        let kinds: Vec<MethodKind> = methods.iter().map(|method| method.kind).collect();
+        assert!(vec![MethodKind::Instance, MethodKind::Static, MethodKind::Getter, MethodKind::Setter, MethodKind::Instance] == kinds);
+        assert!("area" == methods[3].declaration.name.lexeme);
+        assert!("set" == methods[4].declaration.name.lexeme);
+    }
+
+    #[test]
+    fn test_parse_traits_and_with_clauses() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("trait A { a() {} } trait B with A {} class C < D with A, B { c() {} } class E {}", &mut rlox);
+
+        assert!(!rlox.had_error);
+        assert!(matches!(&result[0], Statement::Trait { traits, methods, .. } if traits.is_empty() && methods.len() == 1));
+        assert!(matches!(&result[1], Statement::Trait { traits, .. } if traits.len() == 1));
+        let Statement::Class { superclass: Some(_), traits, .. } = &result[2] else {
+            panic!("Expect a class with a superclass");
+        };
+        let names: Vec<&str> = traits.iter().map(|name| match name {
+            Expression::Variable { name, .. } => name.lexeme.as_str(),
+            _ => panic!("Expect a trait name"),
+        }).collect();
+        assert!(vec!["A", "B"] == names);
+        assert!(matches!(&result[3], Statement::Class { traits, .. } if traits.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_setter_needs_one_parameter() {
+        let mut rlox: RLox = RLox::new();
+        parse("class A { set area(a, b) {} }", &mut rlox);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_parse_doc_comments_attach_to_declarations() {
+        let mut rlox: RLox = RLox::new();
+        let result: Vec<Statement> = parse("/// A point.\nclass Point {\n/// Sums it up.\nsum() {} }\n/// Answer.\nvar a = 42;", &mut rlox);
+
+        assert!(!rlox.had_error);
+        let Statement::Class { doc, methods, .. } = &result[0] else {
+            panic!("Expect a class declaration");
+        };
+        assert!(Some("A point.".to_string()) == *doc);
+        assert!(Some("Sums it up.".to_string()) == methods[0].declaration.doc);
+        let Statement::Var { doc, .. } = &result[1] else {
+            panic!("Expect a variable declaration");
+        };
+        assert!(Some("Answer.".to_string()) == *doc);
+    }
 }
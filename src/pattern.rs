@@ -0,0 +1,37 @@
+use crate::{expression::Expression, object::Object, range::Range, token::Token};
+
+// The left side of a match arm
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // A number, string, boolean or nil, compared with ==
+    Literal {
+        value: Object,
+    },
+    // 1..5 or 1..=5, matches the numbers in the range
+    Range {
+        range: Range,
+    },
+    // Matches anything and binds it to 'name'
+    Binding {
+        name: Token,
+    },
+    // _ matches anything without binding it
+    Wildcard,
+    // [first, second, ..rest], 'rest' is a binding or wildcard for the remaining elements
+    List {
+        elements: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+    // Point { x: 0, y }, an instance of the class or a subclass whose fields match
+    Instance {
+        class: Expression,
+        fields: Vec<(Token, Pattern)>,
+    },
+}
+
+impl Pattern {
+    // Whether the pattern matches every value, so later arms can't be reached
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Binding { .. })
+    }
+}
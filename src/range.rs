@@ -0,0 +1,57 @@
+use std::{fmt, rc::Rc};
+
+use crate::{function::NativeFunction, object::Object, token::Token};
+
+// start..end or start..=end over integers, iterated lazily so a big range costs nothing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Self { start, end, inclusive }
+    }
+
+    // The first value past the end
+    pub fn stop(&self) -> i64 {
+        if self.inclusive { self.end.saturating_add(1) } else { self.end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stop().saturating_sub(self.start).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        let start: f64 = self.start as f64;
+        let end: f64 = self.end as f64;
+        if self.inclusive { start <= value && value <= end } else { start <= value && value < end }
+    }
+
+    pub fn method(range: Range, name: &Token) -> Option<NativeFunction> {
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "contains" => NativeFunction::new("contains", 1, Rc::new(move |_, _, arguments| {
+                let contained: bool = arguments[0].as_float().is_some_and(|number| range.contains(number));
+                Ok(Object::Boolean(contained))
+            })),
+            "len" => NativeFunction::new("len", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Integer(range.len() as i64))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator: &str = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, operator, self.end)
+    }
+}
@@ -0,0 +1,188 @@
+use std::{cmp::Ordering, fmt, rc::Rc};
+
+use crate::{bigint::BigInt, function::NativeFunction, object::Object, token::Token};
+
+// An exact fraction, always in lowest terms with a positive denominator, so each value has one
+// representation and the derived equality and hashing are by value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    // None for a zero denominator
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+
+        let divisor: BigInt = numerator.gcd(&denominator);
+        let (numerator, _) = numerator.divide(&divisor).unwrap();
+        let (denominator, _) = denominator.divide(&divisor).unwrap();
+        if denominator.is_negative() {
+            return Some(Self { numerator: numerator.negate(), denominator: denominator.negate() });
+        }
+        Some(Self { numerator, denominator })
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == BigInt::from(1_i64)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numerator.to_f64() / self.denominator.to_f64()
+    }
+
+    // Finite floats are fractions with a power of two below, so they convert exactly
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        let bits: u64 = value.to_bits();
+        let exponent: i64 = ((bits >> 52) & 0x7ff) as i64;
+        let fraction: u64 = bits & ((1 << 52) - 1);
+        // Subnormals have no implicit leading bit
+        let (mantissa, shift) = if exponent == 0 { (fraction, -1074) } else { (fraction | (1 << 52), exponent - 1075) };
+
+        let mut numerator: BigInt = BigInt::from(mantissa);
+        if value < 0.0 {
+            numerator = numerator.negate();
+        }
+        if shift >= 0 {
+            return Self::new(numerator.shift_left(shift as usize), BigInt::from(1_i64));
+        }
+        Self::new(numerator, BigInt::from(1_i64).shift_left(-shift as usize))
+    }
+
+    pub fn negate(&self) -> Self {
+        Self { numerator: self.numerator.negate(), denominator: self.denominator.clone() }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let numerator: BigInt = self.numerator.multiply(&other.denominator).add(&other.numerator.multiply(&self.denominator));
+        Self::new(numerator, self.denominator.multiply(&other.denominator)).unwrap()
+    }
+
+    pub fn subtract(&self, other: &Self) -> Self {
+        self.add(&other.negate())
+    }
+
+    pub fn multiply(&self, other: &Self) -> Self {
+        Self::new(self.numerator.multiply(&other.numerator), self.denominator.multiply(&other.denominator)).unwrap()
+    }
+
+    // None when dividing by zero
+    pub fn divide(&self, other: &Self) -> Option<Self> {
+        Self::new(self.numerator.multiply(&other.denominator), self.denominator.multiply(&other.numerator))
+    }
+
+    // What is left after taking out a whole number of 'other' rounded toward zero, so the
+    // remainder has the sign of 'self' like the integer and float '%'
+    pub fn remainder(&self, other: &Self) -> Option<Self> {
+        let quotient: Self = self.divide(other)?;
+        let (whole, _) = quotient.numerator.divide(&quotient.denominator).unwrap();
+        Some(self.subtract(&other.multiply(&Self::from(whole))))
+    }
+
+    // None for a negative power of zero, or when the result is too large to compute
+    pub fn pow(&self, exponent: &BigInt) -> Option<Self> {
+        let power: BigInt = exponent.abs();
+        let raised: Self = Self { numerator: self.numerator.pow(&power)?, denominator: self.denominator.pow(&power)? };
+        if exponent.is_negative() {
+            return Self::new(raised.denominator, raised.numerator);
+        }
+        Some(raised)
+    }
+
+    pub fn method(rational: &Rational, name: &Token) -> Option<NativeFunction> {
+        let rational: Rational = rational.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "numerator" => NativeFunction::new("numerator", 0, Rc::new(move |_, _, _| {
+                Ok(Object::from_big_int(rational.numerator.clone()))
+            })),
+            "denominator" => NativeFunction::new("denominator", 0, Rc::new(move |_, _, _| {
+                Ok(Object::from_big_int(rational.denominator.clone()))
+            })),
+            "float" => NativeFunction::new("float", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Number(rational.to_f64()))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(integer: BigInt) -> Self {
+        Self { numerator: integer, denominator: BigInt::from(1_i64) }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Denominators are positive, so cross multiplying keeps the order
+        self.numerator.multiply(&other.denominator).cmp(&other.numerator.multiply(&self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Whole values print without a denominator
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            return write!(f, "{}", self.numerator);
+        }
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[cfg(test)]
+mod rational_tests {
+    use crate::{bigint::BigInt, rational::Rational};
+
+    fn rational(numerator: i64, denominator: i64) -> Rational {
+        Rational::new(BigInt::from(numerator), BigInt::from(denominator)).unwrap()
+    }
+
+    #[test]
+    fn test_normalized() {
+        assert!(rational(1, 2) == rational(3, 6));
+        assert!(rational(1, -2) == rational(-1, 2));
+        assert!("-1/2" == rational(3, -6).to_string());
+        assert!("0" == rational(0, -5).to_string());
+        assert!(Rational::new(BigInt::from(1_i64), BigInt::zero()).is_none());
+    }
+
+    #[test]
+    fn test_floats_convert_exactly() {
+        assert!(Some(rational(1, 2)) == Rational::from_f64(0.5));
+        assert!(Some(rational(-3, 4)) == Rational::from_f64(-0.75));
+        assert!(Some(rational(12, 1)) == Rational::from_f64(12.0));
+        assert!(Rational::from_f64(0.1) != Some(rational(1, 10)));
+        assert!(Rational::from_f64(f64::NAN).is_none());
+        assert!(0.1 == rational(1, 10).to_f64());
+    }
+
+    #[test]
+    fn test_order() {
+        assert!(rational(1, 3) < rational(1, 2));
+        assert!(rational(-1, 2) < rational(-1, 3));
+        assert!(rational(7, 2).remainder(&rational(-2, 1)) == Some(rational(3, 2)));
+    }
+}
@@ -0,0 +1,625 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    expression::{Expression, ExpressionVisitor},
+    object::Object,
+    pattern::Pattern,
+    rlox::RLox,
+    statement::{CatchClause, FunctionDeclaration, MatchArm, Method, MethodKind, Statement, StatementVisitor},
+    token::Token,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+    Trait,
+    // Inside a static method, where there is no instance
+    Static,
+}
+
+// Works out how many scopes away every local variable is, and reports the
+// errors that can be found without running the program
+pub struct Resolver<'a> {
+    // Per scope, whether each declared name has finished its initializer
+    scopes: Vec<HashMap<String, bool>>,
+    locals: HashMap<usize, usize>,
+    current_function: FunctionType,
+    // Whether the current function yields, generators can't return a value
+    in_generator: bool,
+    current_class: ClassType,
+    loop_depth: usize,
+
+    rlox: &'a mut RLox
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(rlox: &'a mut RLox) -> Self {
+        Self {
+            scopes: Vec::new(),
+            locals: HashMap::new(),
+            current_function: FunctionType::None,
+            in_generator: false,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            rlox,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    // Scope distance of every resolved expression id, globals are left out
+    pub fn into_locals(self) -> HashMap<usize, usize> {
+        self.locals
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        statement.accept(self)
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        expression.accept(self)
+    }
+
+    fn resolve_function(&mut self, declaration: &FunctionDeclaration, function_type: FunctionType) {
+        let enclosing_function: FunctionType = self.current_function;
+        let enclosing_generator: bool = self.in_generator;
+        let enclosing_loop_depth: usize = self.loop_depth;
+        self.current_function = function_type;
+        self.in_generator = declaration.generator.is_some();
+        // A loop around the function does not make 'break' valid inside it
+        self.loop_depth = 0;
+
+        self.begin_scope();
+        for param in &declaration.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&declaration.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.in_generator = enclosing_generator;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    // The methods of a class or trait body, 'current_class' is already set for the body
+    fn resolve_methods(&mut self, methods: &[Method]) {
+        self.begin_scope();
+        self.scopes.last_mut().unwrap().insert("this".to_string(), true);
+
+        for method in methods.iter().filter(|method| method.kind != MethodKind::Static) {
+            let is_initializer: bool = method.kind == MethodKind::Instance && method.declaration.name.lexeme == "init";
+            let function_type: FunctionType = if is_initializer { FunctionType::Initializer } else { FunctionType::Method };
+            self.resolve_function(&method.declaration, function_type);
+        }
+
+        self.end_scope();
+
+        // Static methods are never bound, so they sit outside the scope holding 'this'
+        let class_type: ClassType = self.current_class;
+        self.current_class = ClassType::Static;
+        for method in methods.iter().filter(|method| method.kind == MethodKind::Static) {
+            self.resolve_function(&method.declaration, FunctionType::Method);
+        }
+        self.current_class = class_type;
+    }
+
+    // Declares the names a pattern binds in the current scope
+    fn resolve_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal { .. } | Pattern::Range { .. } | Pattern::Wildcard => {},
+            Pattern::Binding { name } => {
+                self.declare(name);
+                self.define(name);
+            },
+            Pattern::List { elements, rest } => {
+                for element in elements {
+                    self.resolve_pattern(element);
+                }
+                if let Some(rest) = rest {
+                    self.resolve_pattern(rest);
+                }
+            },
+            Pattern::Instance { class, fields } => {
+                self.resolve_expression(class);
+                for (_, field) in fields {
+                    self.resolve_pattern(field);
+                }
+            },
+        }
+    }
+
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (distance, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.locals.insert(id, distance);
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.lexeme) {
+            self.rlox.token_error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+}
+
+impl<'a> ExpressionVisitor<()> for Resolver<'a> {
+    fn visit_assign_expression(&mut self, id: usize, name: &Token, value: &Expression) {
+        self.resolve_expression(value);
+        self.resolve_local(id, name);
+    }
+
+    fn visit_binary_expression(&mut self, left: &Expression, _operator: &Token, right: &Expression) {
+        self.resolve_expression(left);
+        self.resolve_expression(right);
+    }
+
+    fn visit_call_expression(&mut self, callee: &Expression, _paren: &Token, arguments: &[Expression]) {
+        self.resolve_expression(callee);
+        for argument in arguments {
+            self.resolve_expression(argument);
+        }
+    }
+
+    fn visit_compound_assign_expression(&mut self, id: usize, name: &Token, _operator: &Token, value: &Expression, _postfix: bool) {
+        self.resolve_expression(value);
+        self.resolve_local(id, name);
+    }
+
+    fn visit_compound_set_expression(&mut self, object: &Expression, _name: &Token, _operator: &Token, value: &Expression, _postfix: bool) {
+        self.resolve_expression(object);
+        self.resolve_expression(value);
+    }
+
+    fn visit_compound_set_index_expression(&mut self, object: &Expression, _bracket: &Token, index: &Expression, _operator: &Token, value: &Expression, _postfix: bool) {
+        self.resolve_expression(object);
+        self.resolve_expression(index);
+        self.resolve_expression(value);
+    }
+
+    fn visit_conditional_expression(&mut self, condition: &Expression, then_branch: &Expression, else_branch: &Expression) {
+        self.resolve_expression(condition);
+        self.resolve_expression(then_branch);
+        self.resolve_expression(else_branch);
+    }
+
+    fn visit_function_expression(&mut self, declaration: &Rc<FunctionDeclaration>) {
+        self.resolve_function(declaration, FunctionType::Function);
+    }
+
+    fn visit_get_expression(&mut self, object: &Expression, _name: &Token) {
+        self.resolve_expression(object);
+    }
+
+    fn visit_grouping_expression(&mut self, expression: &Expression) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_index_expression(&mut self, object: &Expression, _bracket: &Token, index: &Expression) {
+        self.resolve_expression(object);
+        self.resolve_expression(index);
+    }
+
+    fn visit_interpolation_expression(&mut self, parts: &[Expression]) {
+        for part in parts {
+            self.resolve_expression(part);
+        }
+    }
+
+    fn visit_list_expression(&mut self, elements: &[Expression]) {
+        for element in elements {
+            self.resolve_expression(element);
+        }
+    }
+
+    fn visit_literal_expression(&mut self, _value: &Object) {}
+
+    fn visit_map_expression(&mut self, _brace: &Token, entries: &[(Expression, Expression)]) {
+        for (key, value) in entries {
+            self.resolve_expression(key);
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_logical_expression(&mut self, left: &Expression, _operator: &Token, right: &Expression) {
+        self.resolve_expression(left);
+        self.resolve_expression(right);
+    }
+
+    fn visit_set_expression(&mut self, object: &Expression, _name: &Token, value: &Expression) {
+        self.resolve_expression(value);
+        self.resolve_expression(object);
+    }
+
+    fn visit_set_index_expression(&mut self, object: &Expression, _bracket: &Token, index: &Expression, value: &Expression) {
+        self.resolve_expression(value);
+        self.resolve_expression(object);
+        self.resolve_expression(index);
+    }
+
+    fn visit_super_expression(&mut self, id: usize, keyword: &Token, _method: &Token) {
+        match self.current_class {
+            ClassType::None => self.rlox.token_error(keyword, "Can't use 'super' outside of a class."),
+            ClassType::Class => self.rlox.token_error(keyword, "Can't use 'super' in a class with no superclass."),
+            ClassType::Subclass => {},
+            ClassType::Trait => self.rlox.token_error(keyword, "Can't use 'super' in a trait."),
+            ClassType::Static => self.rlox.token_error(keyword, "Can't use 'super' in a static method."),
+        }
+        self.resolve_local(id, keyword);
+    }
+
+    fn visit_this_expression(&mut self, id: usize, keyword: &Token) {
+        match self.current_class {
+            ClassType::None => {
+                self.rlox.token_error(keyword, "Can't use 'this' outside of a class.");
+                return;
+            },
+            ClassType::Static => {
+                self.rlox.token_error(keyword, "Can't use 'this' in a static method.");
+                return;
+            },
+            ClassType::Class | ClassType::Subclass | ClassType::Trait => {},
+        }
+        self.resolve_local(id, keyword);
+    }
+
+    fn visit_unary_expression(&mut self, _operator: &Token, right: &Expression) {
+        self.resolve_expression(right);
+    }
+
+    fn visit_variable_expression(&mut self, id: usize, name: &Token) {
+        let in_own_initializer: bool = self.scopes.last()
+            .and_then(|scope| scope.get(&name.lexeme))
+            .is_some_and(|defined| !defined);
+        if in_own_initializer {
+            self.rlox.token_error(name, "Can't read local variable in its own initializer.");
+        }
+
+        self.resolve_local(id, name);
+    }
+}
+
+impl<'a> StatementVisitor<()> for Resolver<'a> {
+    fn visit_block_statement(&mut self, statements: &[Statement]) {
+        self.begin_scope();
+        self.resolve(statements);
+        self.end_scope();
+    }
+
+    fn visit_break_statement(&mut self, keyword: &Token) {
+        if self.loop_depth == 0 {
+            self.rlox.token_error(keyword, "Can't use 'break' outside of a loop.");
+        }
+    }
+
+    fn visit_class_statement(&mut self, name: &Token, superclass: Option<&Expression>, traits: &[Expression], methods: &[Method]) {
+        let enclosing_class: ClassType = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        // Outside the scope holding 'super', like the superclass itself
+        for mixed in traits {
+            self.resolve_expression(mixed);
+        }
+
+        if let Some(superclass) = superclass {
+            if let Expression::Variable { name: superclass_name, .. } = superclass {
+                if superclass_name.lexeme == name.lexeme {
+                    self.rlox.token_error(superclass_name, "A class can't inherit from itself.");
+                }
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expression(superclass);
+
+            self.begin_scope();
+            self.scopes.last_mut().unwrap().insert("super".to_string(), true);
+        }
+
+        self.resolve_methods(methods);
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_continue_statement(&mut self, keyword: &Token) {
+        if self.loop_depth == 0 {
+            self.rlox.token_error(keyword, "Can't use 'continue' outside of a loop.");
+        }
+    }
+
+    fn visit_expression_statement(&mut self, expression: &Expression) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_for_in_statement(&mut self, name: &Token, _keyword: &Token, iterable: &Expression, body: &Statement) {
+        self.resolve_expression(iterable);
+
+        // Every iteration gets a fresh scope holding the loop variable
+        self.begin_scope();
+        self.declare(name);
+        self.define(name);
+
+        self.loop_depth += 1;
+        self.resolve_statement(body);
+        self.loop_depth -= 1;
+
+        self.end_scope();
+    }
+
+    fn visit_function_statement(&mut self, declaration: &Rc<FunctionDeclaration>) {
+        self.declare(&declaration.name);
+        self.define(&declaration.name);
+        self.resolve_function(declaration, FunctionType::Function);
+    }
+
+    fn visit_if_statement(&mut self, condition: &Expression, then_branch: &Statement, else_branch: Option<&Statement>) {
+        self.resolve_expression(condition);
+        self.resolve_statement(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.resolve_statement(else_branch);
+        }
+    }
+
+    fn visit_match_statement(&mut self, value: &Expression, arms: &[MatchArm]) {
+        self.resolve_expression(value);
+
+        let mut catch_all: bool = false;
+        for arm in arms {
+            if catch_all {
+                self.rlox.warning(&arm.arrow, "Unreachable match arm.");
+            }
+            catch_all |= arm.guard.is_none() && arm.pattern.is_irrefutable();
+
+            // Each arm gets a scope for the names its pattern binds
+            self.begin_scope();
+            self.resolve_pattern(&arm.pattern);
+            if let Some(guard) = &arm.guard {
+                self.resolve_expression(guard);
+            }
+            self.resolve_statement(&arm.body);
+            self.end_scope();
+        }
+    }
+
+    fn visit_print_statement(&mut self, expression: &Expression) {
+        self.resolve_expression(expression);
+    }
+
+    fn visit_return_statement(&mut self, keyword: &Token, value: Option<&Expression>) {
+        if self.current_function == FunctionType::None {
+            self.rlox.token_error(keyword, "Can't return from top-level code.");
+        }
+
+        if let Some(value) = value {
+            if self.current_function == FunctionType::Initializer {
+                self.rlox.token_error(keyword, "Can't return a value from an initializer.");
+            } else if self.in_generator {
+                self.rlox.token_error(keyword, "Can't return a value from a generator.");
+            }
+            self.resolve_expression(value);
+        }
+    }
+
+    fn visit_throw_statement(&mut self, _keyword: &Token, value: &Expression) {
+        self.resolve_expression(value);
+    }
+
+    fn visit_trait_statement(&mut self, name: &Token, traits: &[Expression], methods: &[Method]) {
+        self.declare(name);
+        self.define(name);
+        for mixed in traits {
+            self.resolve_expression(mixed);
+        }
+
+        let enclosing_class: ClassType = self.current_class;
+        self.current_class = ClassType::Trait;
+        self.resolve_methods(methods);
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_try_statement(&mut self, body: &[Statement], catch: Option<&CatchClause>, finally: Option<&[Statement]>) {
+        self.visit_block_statement(body);
+
+        if let Some(catch) = catch {
+            self.begin_scope();
+            self.declare(&catch.name);
+            self.define(&catch.name);
+            self.resolve(&catch.body);
+            self.end_scope();
+        }
+
+        if let Some(finally) = finally {
+            self.visit_block_statement(finally);
+        }
+    }
+
+    fn visit_var_statement(&mut self, name: &Token, initializer: Option<&Expression>) {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expression(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_while_statement(&mut self, condition: &Expression, body: &Statement, increment: Option<&Expression>) {
+        self.resolve_expression(condition);
+
+        self.loop_depth += 1;
+        self.resolve_statement(body);
+        self.loop_depth -= 1;
+
+        if let Some(increment) = increment {
+            self.resolve_expression(increment);
+        }
+    }
+
+    fn visit_yield_statement(&mut self, keyword: &Token, value: Option<&Expression>) {
+        match self.current_function {
+            FunctionType::None => self.rlox.token_error(keyword, "Can't yield from top-level code."),
+            FunctionType::Initializer => self.rlox.token_error(keyword, "Can't yield from an initializer."),
+            FunctionType::Function | FunctionType::Method => {},
+        }
+
+        if let Some(value) = value {
+            self.resolve_expression(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod resolver_tests {
+    use crate::{parser::Parser, rlox::RLox, scanner::Scanner, statement::Statement, token::Token};
+
+    use super::Resolver;
+
+    // Whether resolving 'source' reports an error
+    fn has_error(source: &str) -> bool {
+        resolve(source).had_error
+    }
+
+    fn resolve(source: &str) -> RLox {
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.to_string(), &mut rlox);
+        let tokens: Vec<Token> = scanner.scan_tokens();
+        let mut parser: Parser = Parser::new(tokens, &mut rlox);
+        let statements: Vec<Statement> = parser.parse();
+        assert!(!rlox.had_error, "Syntax error in {}", source);
+
+        let mut resolver: Resolver = Resolver::new(&mut rlox);
+        resolver.resolve(&statements);
+        rlox
+    }
+
+    #[test]
+    fn test_break_and_continue_inside_loops() {
+        assert!(!has_error("while (true) { break; }"));
+        assert!(!has_error("for (;;) { if (true) continue; }"));
+        assert!(!has_error("while (true) { { while (false) break; } break; }"));
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_loops() {
+        assert!(has_error("break;"));
+        assert!(has_error("continue;"));
+        assert!(has_error("if (true) { break; }"));
+        assert!(has_error("while (true) { fun f() { break; } }"));
+    }
+
+    #[test]
+    fn test_break_inside_for_in_loop() {
+        assert!(!has_error("for (x in []) { if (x) break; continue; }"));
+    }
+
+    #[test]
+    fn test_unreachable_match_arms_warn() {
+        let unreachable: RLox = resolve("match (1) { x => print x; 1 => print 1; }");
+        assert!(unreachable.had_warning);
+        assert!(!unreachable.had_error);
+
+        assert!(resolve("match (1) { _ => print 0; _ => print 1; }").had_warning);
+        assert!(!resolve("match (1) { x if x > 0 => print x; _ => print 0; }").had_warning);
+        assert!(!resolve("match (1) { [x] => print x; Point { x } => print x; _ => print 0; }").had_warning);
+    }
+
+    #[test]
+    fn test_match_bindings_are_scoped_to_their_arm() {
+        assert!(has_error("match ([1, 2]) { [x, x] => print x; }"));
+        assert!(!has_error("{ match (1) { x => print x; } var x = 1; }"));
+    }
+
+    #[test]
+    fn test_lambdas_are_functions() {
+        assert!(!has_error("var f = fun () { return 1; }; var g = () => 2;"));
+        assert!(has_error("while (true) { var f = fun () { break; }; }"));
+        assert!(!has_error("{ var fact = (n) => n < 2 ? 1 : n * fact(n - 1); print fact(3); }"));
+    }
+
+    #[test]
+    fn test_static_method_errors() {
+        assert!(has_error("class A { class f() { return this; } }"));
+        assert!(has_error("class A { class f() { fun g() { return this; } } }"));
+        assert!(has_error("class A {} class B < A { class f() { return super.f(); } }"));
+        assert!(!has_error("class A { class f() { class B { g() { return this; } } } }"));
+        assert!(!has_error("class A { x { return this.y; } set x(value) { this.y = value; } }"));
+    }
+
+    #[test]
+    fn test_trait_errors() {
+        assert!(has_error("trait A { f() { return super.f(); } }"));
+        assert!(has_error("trait A { class f() { return this; } }"));
+        assert!(!has_error("trait A { f() { return this.g(); } } class B with A { g() { return 1; } }"));
+    }
+
+    #[test]
+    fn test_yield_errors() {
+        assert!(has_error("yield 1;"));
+        assert!(has_error("class A { init() { yield 1; } }"));
+        assert!(has_error("fun f() { yield 1; return 2; }"));
+        assert!(!has_error("fun f() { yield 1; return; } fun g() { fun h() { yield 1; } return h; }"));
+        assert!(!has_error("class A { iter() { for (x in [1]) { yield x; } } }"));
+    }
+
+    #[test]
+    fn test_variable_errors() {
+        assert!(has_error("{ var a = a; }"));
+        assert!(has_error("{ var a = 1; var a = 2; }"));
+        assert!(!has_error("var a = 1; var a = 2;"));
+    }
+
+    #[test]
+    fn test_return_errors() {
+        assert!(has_error("return 1;"));
+        assert!(has_error("class A { init() { return 1; } }"));
+        assert!(!has_error("class A { init() { return; } }"));
+    }
+
+    #[test]
+    fn test_class_errors() {
+        assert!(has_error("print this;"));
+        assert!(has_error("fun f() { super.g(); }"));
+        assert!(has_error("class A { f() { super.f(); } }"));
+        assert!(has_error("class A < A {}"));
+    }
+}
@@ -1,14 +1,28 @@
-use std::{fs, io, process};
+use std::{collections::HashMap, fs, io, process};
 
-use crate::{expression::Expression, parser::Parser, scanner::Scanner, token::Token};
+use crate::{dialect::Dialect, interpreter::{Interpreter, RuntimeError}, parser::Parser, resolver::Resolver, scanner::Scanner, statement::Statement, token::Token, token_type::TokenType};
 
 pub struct RLox {
-    had_error: bool,
+    pub had_error: bool,
+    pub had_runtime_error: bool,
+    pub had_warning: bool,
+    pub dialect: Dialect,
+    interpreter: Interpreter,
+}
+
+impl Default for RLox {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RLox {
     pub fn new() -> Self {
-        RLox { had_error: false }
+        Self::with_dialect(Dialect::default())
+    }
+
+    pub fn with_dialect(dialect: Dialect) -> Self {
+        RLox { had_error: false, had_runtime_error: false, had_warning: false, dialect, interpreter: Interpreter::new() }
     }
 
     pub fn run_file(&mut self, file_path: &String) {
@@ -18,6 +32,9 @@ impl RLox {
         if self.had_error {
             process::exit(65);
         }
+        if self.had_runtime_error {
+            process::exit(70);
+        }
     }
 
     pub fn run_prompt(&mut self) {
@@ -43,20 +60,52 @@ impl RLox {
         let mut scanner = Scanner::new(source, self);
         let tokens: Vec<Token> = scanner.scan_tokens();
 
-        println!("\n\n[TOKENS]:\n\n{:?}\n\n", &tokens);
+        let mut parser: Parser = Parser::new(tokens, self);
+        let statements: Vec<Statement> = parser.parse();
+
+        if self.had_error {
+            return;
+        }
+
+        let mut resolver: Resolver = Resolver::new(self);
+        resolver.resolve(&statements);
+        let locals: HashMap<usize, usize> = resolver.into_locals();
 
-        let mut parser: Parser = Parser::new(tokens);
-        let expression: Expression = parser.parse();
+        if self.had_error {
+            return;
+        }
 
-        println!("\n\n[AST]:\n\n{:?}\n\n", expression);
+        self.interpreter.resolve(locals);
+        if let Err(error) = self.interpreter.interpret(&statements) {
+            self.runtime_error(error);
+        }
     }
 
     pub fn error(&mut self, line: u64, message: String) {
         self.report(line, String::new(), message);
     }
 
+    pub fn token_error(&mut self, token: &Token, message: &str) {
+        if token.token_type == TokenType::Eof {
+            self.report(token.line as u64, "at end".to_string(), message.to_string());
+        } else {
+            self.report(token.line as u64, format!("at '{}'", token.lexeme), message.to_string());
+        }
+    }
+
+    // Reported like an error, but the program still runs
+    pub fn warning(&mut self, token: &Token, message: &str) {
+        println!("[line {}] Warning at '{}': {}", token.line, token.lexeme, message);
+        self.had_warning = true;
+    }
+
     pub fn report(&mut self, line: u64, location: String, message: String) {
         println!("[line {}] Error {}: {}", line, location, message);
         self.had_error = true;
     }
+
+    pub fn runtime_error(&mut self, error: RuntimeError) {
+        println!("{}\n[line {}]", error.message, error.token.line);
+        self.had_runtime_error = true;
+    }
 }
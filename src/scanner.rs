@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 
-use crate::{object::Object, rlox::RLox, token::Token, token_type::TokenType};
+use crate::{bigint::BigInt, object::Object, rlox::RLox, token::Token, token_type::TokenType};
 
 pub struct Scanner<'a> {
     keywords: HashMap<String, TokenType>,
-    source: String,
+    source: Vec<char>,
     tokens: Vec<Token>,
+    docs: Vec<String>,
+    // Brace depth of every "${" we are currently inside of
+    interpolations: Vec<u64>,
 
     start: u64,
     current: u64,
@@ -24,7 +27,6 @@ impl<'a> Scanner<'a> {
         keywords.insert("for".to_string(),      TokenType::For);
         keywords.insert("fun".to_string(),      TokenType::Fun);
         keywords.insert("if".to_string(),       TokenType::If);
-        keywords.insert("null".to_string(),     TokenType::Null);
         keywords.insert("or".to_string(),       TokenType::Or);
         keywords.insert("print".to_string(),    TokenType::Print);
         keywords.insert("return".to_string(),   TokenType::Return);
@@ -33,11 +35,27 @@ impl<'a> Scanner<'a> {
         keywords.insert("true".to_string(),     TokenType::True);
         keywords.insert("var".to_string(),      TokenType::Var);
         keywords.insert("while".to_string(),    TokenType::While);
+        keywords.insert(rlox.dialect.nil_keyword().to_string(), TokenType::Nil);
+
+        if rlox.dialect.has_extensions() {
+            keywords.insert("break".to_string(),    TokenType::Break);
+            keywords.insert("continue".to_string(), TokenType::Continue);
+            keywords.insert("in".to_string(),       TokenType::In);
+            keywords.insert("throw".to_string(),    TokenType::Throw);
+            keywords.insert("try".to_string(),      TokenType::Try);
+            keywords.insert("catch".to_string(),    TokenType::Catch);
+            keywords.insert("finally".to_string(),  TokenType::Finally);
+            keywords.insert("match".to_string(),    TokenType::Match);
+            keywords.insert("trait".to_string(),    TokenType::Trait);
+            keywords.insert("yield".to_string(),    TokenType::Yield);
+        }
 
         Self {
             keywords,
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
+            docs: Vec::new(),
+            interpolations: Vec::new(),
 
             start: 0,
             current: 0,
@@ -64,36 +82,104 @@ impl<'a> Scanner<'a> {
         match c {
             '(' => self.add_token(TokenType::LeftParen, Object::Null),
             ')' => self.add_token(TokenType::RightParen, Object::Null),
-            '{' => self.add_token(TokenType::LeftBrace, Object::Null),
-            '}' => self.add_token(TokenType::RightBrace, Object::Null),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace, Object::Null);
+            },
+            '}' => {
+                match self.interpolations.last_mut() {
+                    // Closes the embedded expression, the rest of the string follows
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.string();
+                    },
+                    Some(depth) => {
+                        *depth -= 1;
+                        self.add_token(TokenType::RightBrace, Object::Null);
+                    },
+                    None => self.add_token(TokenType::RightBrace, Object::Null),
+                }
+            },
+            '[' if self.extended() => self.add_token(TokenType::LeftBracket, Object::Null),
+            ']' if self.extended() => self.add_token(TokenType::RightBracket, Object::Null),
             ',' => self.add_token(TokenType::Comma, Object::Null),
-            '.' => self.add_token(TokenType::Dot, Object::Null),
-            '-' => self.add_token(TokenType::Minus, Object::Null),
-            '+' => self.add_token(TokenType::Plus, Object::Null),
+            '.' => {
+                let token_type: TokenType = if !self.extended() || !self.match_next('.') { TokenType::Dot }
+                    else if self.match_next('=') { TokenType::DotDotEqual }
+                    else { TokenType::DotDot };
+                self.add_token(token_type, Object::Null);
+            },
+            '-' => {
+                let token_type: TokenType = if !self.extended() { TokenType::Minus }
+                    else if self.match_next('-') { TokenType::MinusMinus }
+                    else if self.match_next('=') { TokenType::MinusEqual }
+                    else { TokenType::Minus };
+                self.add_token(token_type, Object::Null);
+            },
+            '+' => {
+                let token_type: TokenType = if !self.extended() { TokenType::Plus }
+                    else if self.match_next('+') { TokenType::PlusPlus }
+                    else if self.match_next('=') { TokenType::PlusEqual }
+                    else { TokenType::Plus };
+                self.add_token(token_type, Object::Null);
+            },
             ';' => self.add_token(TokenType::Semicolon, Object::Null),
-            '*' => self.add_token(TokenType::Star, Object::Null),
+            '*' => {
+                let token_type: TokenType = if !self.extended() { TokenType::Star }
+                    else if self.match_next('*') { TokenType::StarStar }
+                    else if self.match_next('=') { TokenType::StarEqual }
+                    else { TokenType::Star };
+                self.add_token(token_type, Object::Null);
+            },
+            '%' if self.extended() => {
+                let token_type: TokenType = if self.match_next('=') { TokenType::PercentEqual } else { TokenType::Percent };
+                self.add_token(token_type, Object::Null);
+            },
+            '&' if self.extended() => self.add_token(TokenType::Ampersand, Object::Null),
+            '|' if self.extended() => self.add_token(TokenType::Pipe, Object::Null),
+            '^' if self.extended() => self.add_token(TokenType::Caret, Object::Null),
+            '~' if self.extended() => self.add_token(TokenType::Tilde, Object::Null),
+            '?' if self.extended() => self.add_token(TokenType::Question, Object::Null),
+            ':' if self.extended() => self.add_token(TokenType::Colon, Object::Null),
             '!' => {
                 let token_type: TokenType = if self.match_next('=') { TokenType::BangEqual } else { TokenType::Bang };
                 self.add_token(token_type, Object::Null);
             },
             '=' => {
-                let token_type: TokenType = if self.match_next('=') { TokenType::EqualEqual } else { TokenType::Equal };
+                let token_type: TokenType = if self.match_next('=') { TokenType::EqualEqual }
+                    else if self.extended() && self.match_next('>') { TokenType::FatArrow }
+                    else { TokenType::Equal };
                 self.add_token(token_type, Object::Null);
             },
             '<' => {
-                let token_type: TokenType = if self.match_next('=') { TokenType::LessEqual } else { TokenType::Less };
+                let token_type: TokenType = if self.match_next('=') { TokenType::LessEqual }
+                    else if self.extended() && self.match_next('<') { TokenType::LessLess }
+                    else { TokenType::Less };
                 self.add_token(token_type, Object::Null);
             },
             '>' => {
-                let token_type: TokenType = if self.match_next('=') { TokenType::GreaterEqual } else { TokenType::Greater };
+                let token_type: TokenType = if self.match_next('=') { TokenType::GreaterEqual }
+                    else if self.extended() && self.match_next('>') { TokenType::GreaterGreater }
+                    else { TokenType::Greater };
                 self.add_token(token_type, Object::Null);
             },
             '/' => {
-                // Single line comment
                 if self.match_next('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
+                    // Doc comment, `////` and longer are plain comments
+                    if self.extended() && self.peek() == '/' && self.peek_next() != '/' {
+                        self.doc_comment();
+                    } else {
+                        // Single line comment
+                        while self.peek() != '\n' && !self.is_at_end() {
+                            self.advance();
+                        }
                     }
+                } else if self.extended() && self.match_next('*') {
+                    self.block_comment();
+                } else if self.extended() && self.match_next('=') {
+                    self.add_token(TokenType::SlashEqual, Object::Null);
                 } else {
                     self.add_token(TokenType::Slash, Object::Null);
                 }
@@ -106,15 +192,52 @@ impl<'a> Scanner<'a> {
             _ => {
                 if self.is_digit(c) {
                     self.number();
+                } else if c == 'r' && self.extended() && (self.peek() == '"' || self.peek() == '#') {
+                    self.raw_string();
                 } else if self.is_alpha(c) {
                     self.identifier();
                 } else {
-                    self.rlox.error(self.line, "Unexpected character".to_string());
+                    self.error_token(&format!("Unexpected character '{}'", c));
                 }
             }
         }
     }
 
+    // Doc comment
+    fn doc_comment(&mut self) {
+        // The third '/'
+        self.advance();
+
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+
+        let text: String = self.text(self.start + 3, self.current);
+        let text: &str = text.strip_prefix(' ').unwrap_or(&text);
+        self.docs.push(text.trim_end().to_string());
+    }
+
+    // Block comment, these can be nested
+    fn block_comment(&mut self) {
+        let mut depth: u64 = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error_token("Unterminated block comment");
+                return;
+            }
+
+            let c: char = self.advance();
+            if c == '\n' {
+                self.line += 1;
+            } else if c == '/' && self.match_next('*') {
+                depth += 1;
+            } else if c == '*' && self.match_next('/') {
+                depth -= 1;
+            }
+        }
+    }
+
     // Identifier
     fn identifier(&mut self) { 
         let mut current_char: char = self.peek();
@@ -122,68 +245,290 @@ impl<'a> Scanner<'a> {
             self.advance();
             current_char = self.peek();
         }
-        let text: String = self.source[self.start as usize .. self.current as usize].to_string();
+        let text: String = self.text(self.start, self.current);
         let token_type: TokenType = self.keywords.get(&text)
-            .or(Some(&TokenType::Identifier))
-            .unwrap()
-            .to_owned();
+            .cloned()
+            .unwrap_or(TokenType::Identifier);
         self.add_token(token_type, Object::Null);
     }
 
-    // Number
+    // Number, decimal with optional fraction and exponent, or 0x / 0b / 0o prefixed integers
     fn number(&mut self) {
-        let mut current_char = self.peek();
-        while self.is_digit(current_char) {
-            self.advance();
-            current_char = self.peek();
+        let first_char: char = self.source[self.start as usize];
+        let radix: Option<u32> = match self.peek() {
+            'x' | 'X' => Some(16),
+            'b' | 'B' => Some(2),
+            'o' | 'O' => Some(8),
+            _ => None,
+        };
+        if let Some(radix) = radix.filter(|_| self.extended() && first_char == '0') {
+            self.radix_number(radix);
+            return;
         }
 
+        self.decimal_digits();
+
         let next_char = self.peek_next();
-        if current_char == '.' && self.is_digit(next_char) {
+        if self.peek() == '.' && self.is_digit(next_char) {
             self.advance();
-            let mut current_char = self.peek();
-            while self.is_digit(current_char) {
+            self.decimal_digits();
+        }
+
+        if self.extended() && (self.peek() == 'e' || self.peek() == 'E') {
+            let next_char: char = self.peek_next();
+            let sign: bool = next_char == '+' || next_char == '-';
+            let exponent_start: u64 = if sign { self.current + 2 } else { self.current + 1 };
+            let exponent_char: char = self.source.get(exponent_start as usize).copied().unwrap_or('\0');
+
+            if !self.is_digit(exponent_char) {
                 self.advance();
-                current_char = self.peek();
+                self.error_token("Expect digits in number exponent");
+                return;
             }
+
+            self.current = exponent_start;
+            self.decimal_digits();
+        }
+
+        let text: String = self.text(self.start, self.current);
+        if !Self::valid_separators(&text, |c| c.is_ascii_digit()) {
+            self.error_token("Digit separators must be between digits");
+            return;
+        }
+
+        // Without a fraction or an exponent the extended dialect makes an integer
+        let digits: String = text.replace('_', "");
+        if self.extended() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            self.integer_literal(&digits, 10);
+            return;
         }
 
-        let text = self.source[self.start as usize .. self.current as usize].to_string();
-        self.add_token(TokenType::Number, Object::Number(text.parse::<f64>().unwrap()));
+        match digits.parse::<f64>() {
+            Ok(value) => self.add_token(TokenType::Number, Object::Number(value)),
+            Err(_) => self.error_token("Malformed number literal"),
+        }
     }
 
-    // String
+    fn radix_number(&mut self, radix: u32) {
+        // The 'x', 'b' or 'o'
+        self.advance();
+
+        // Take every alphanumeric character so '0b102' is reported as a whole
+        while self.peek().is_ascii_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+
+        let text: String = self.text(self.start, self.current);
+        let digits: String = text[2..].replace('_', "");
+
+        if digits.is_empty() {
+            self.error_token(&format!("Expect digits after '{}'", &text[..2]));
+            return;
+        }
+
+        if let Some(invalid) = digits.chars().find(|c| !c.is_digit(radix)) {
+            self.error_token(&format!("Invalid digit '{}' in base {} number literal", invalid, radix));
+            return;
+        }
+
+        if !Self::valid_separators(&text[2..], |c| c.is_digit(radix)) {
+            self.error_token("Digit separators must be between digits");
+            return;
+        }
+
+        self.integer_literal(&digits, radix);
+    }
+
+    // Literals past the i64 range are big integers
+    fn integer_literal(&mut self, digits: &str, radix: u32) {
+        let value: Object = match i64::from_str_radix(digits, radix) {
+            Ok(value) => Object::Integer(value),
+            Err(_) => Object::BigInt(BigInt::parse(digits, radix).expect("Digits are checked while scanning")),
+        };
+        self.add_token(TokenType::Number, value);
+    }
+
+    // Digits, possibly grouped with '_' separators
+    fn decimal_digits(&mut self) {
+        let mut current_char: char = self.peek();
+        while self.is_digit(current_char) || (current_char == '_' && self.extended()) {
+            self.advance();
+            current_char = self.peek();
+        }
+    }
+
+    // Every '_' has to sit between two digits
+    fn valid_separators(text: &str, is_digit: impl Fn(char) -> bool) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        chars.iter().enumerate()
+            .filter(|(_, c)| **c == '_')
+            .all(|(i, _)| i > 0 && i + 1 < chars.len() && is_digit(chars[i - 1]) && is_digit(chars[i + 1]))
+    }
+
+    // String, or the segment of one that ends where an interpolated expression starts
     fn string(&mut self) {
+        let mut value: String = String::new();
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' { self.line += 1; }
-            self.advance();
+            let c: char = self.advance();
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    value.push(c);
+                },
+                '$' if self.extended() && self.match_next('{') => {
+                    self.interpolations.push(0);
+                    self.add_token(TokenType::Interpolation, Object::String(value));
+                    return;
+                },
+                '\\' if self.extended() => {
+                    if let Some(escaped) = self.escape_sequence() {
+                        value.push(escaped);
+                    }
+                },
+                _ => value.push(c),
+            }
         }
 
         if self.is_at_end() {
-            self.rlox.error(self.line, "Unterminated String".to_string());
+            self.error_token("Unterminated string");
+            return;
         }
 
         // The closing '"'
         self.advance();
 
-        // Trim the surrounding quotes
-        let literal: String = self.source[(self.start + 1) as usize .. (self.current - 1) as usize].to_string();
+        self.add_token(TokenType::String, Object::String(value));
+    }
+
+    // Raw string, r"..." or r#"..."# when the string itself contains quotes
+    fn raw_string(&mut self) {
+        let mut hashes: usize = 0;
+        while self.match_next('#') {
+            hashes += 1;
+        }
+
+        if !self.match_next('"') {
+            self.error_token("Expect '\"' to start raw string");
+            return;
+        }
+
+        let content_start: u64 = self.current;
+        loop {
+            if self.is_at_end() {
+                self.error_token("Unterminated raw string");
+                return;
+            }
+
+            let c: char = self.advance();
+            if c == '\n' {
+                self.line += 1;
+            } else if c == '"' && self.match_hashes(hashes) {
+                break;
+            }
+        }
+
+        // Trim the closing quote and hashes
+        let content_end: u64 = self.current - 1 - hashes as u64;
+        let literal: String = self.text(content_start, content_end);
         self.add_token(TokenType::String, Object::String(literal));
     }
 
+    // Decodes the escape sequence following a '\\', reporting invalid ones
+    fn escape_sequence(&mut self) -> Option<char> {
+        if self.is_at_end() {
+            return None;
+        }
+
+        let c: char = self.advance();
+        let escaped: Option<char> = match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '$' => Some('$'),
+            'u' => self.unicode_escape(),
+            _ => {
+                self.rlox.error(self.line, format!("Invalid escape sequence '\\{}'", c));
+                None
+            }
+        };
+
+        // A line break after the backslash still ends the line
+        if c == '\n' {
+            self.line += 1;
+        }
+        escaped
+    }
+
+    // \u{XXXX} with one to six hex digits
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.match_next('{') {
+            self.rlox.error(self.line, "Expect '{' after '\\u'".to_string());
+            return None;
+        }
+
+        let mut digits: String = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.advance());
+        }
+
+        if !self.match_next('}') {
+            self.rlox.error(self.line, "Expect '}' after unicode escape digits".to_string());
+            return None;
+        }
+
+        if digits.is_empty() || digits.len() > 6 {
+            self.rlox.error(self.line, "Unicode escape must have between 1 and 6 hex digits".to_string());
+            return None;
+        }
+
+        let code: Option<char> = u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32);
+        if code.is_none() {
+            self.rlox.error(self.line, format!("Invalid unicode code point '{}'", digits));
+        }
+        code
+    }
+
     // Helpers
     fn is_at_end(&mut self) -> bool {
-        self.current as usize >= self.source.chars().count()
+        self.current as usize >= self.source.len()
     }
 
     fn advance(&mut self) -> char {
         self.current += 1;
-        self.source.chars().nth((self.current - 1) as usize).unwrap()
+        self.source[(self.current - 1) as usize]
     }
 
     fn add_token(&mut self, token_type: TokenType, literal: Object) {
-        let lexeme: String = self.source[(self.start as usize) .. (self.current as usize)].to_string();
-        self.tokens.push(Token::new(token_type, lexeme, literal, self.line as usize));
+        let lexeme: String = self.text(self.start, self.current);
+        let mut token: Token = Token::new(token_type, lexeme, literal, self.line as usize);
+
+        // Pending doc comments belong to whatever comes right after them
+        if !self.docs.is_empty() {
+            token.doc = Some(self.docs.join("\n"));
+            self.docs.clear();
+        }
+
+        self.tokens.push(token);
+    }
+
+    // Reports the error and leaves an Error token in its place, so scanning goes on
+    // and the parser knows to recover around it without reporting it again
+    fn error_token(&mut self, message: &str) {
+        self.rlox.error(self.line, message.to_string());
+        self.add_token(TokenType::Error, Object::String(message.to_string()));
+    }
+
+    // Whether the rlox extensions are enabled on top of standard Lox
+    fn extended(&self) -> bool {
+        self.rlox.dialect.has_extensions()
+    }
+
+    fn text(&self, start: u64, end: u64) -> String {
+        self.source[start as usize .. end as usize].iter().collect()
     }
 
     // Consumes the next character if it matches the 'expected' value
@@ -192,7 +537,7 @@ impl<'a> Scanner<'a> {
             return false;
         }
 
-        if self.source.chars().nth(self.current as usize).unwrap() != expected {
+        if self.source[self.current as usize] != expected {
             return false;
         }
 
@@ -200,28 +545,38 @@ impl<'a> Scanner<'a> {
         true
     }
 
+    // Consumes 'count' '#' characters if they are all there
+    fn match_hashes(&mut self, count: usize) -> bool {
+        let start: u64 = self.current;
+        for _ in 0..count {
+            if !self.match_next('#') {
+                self.current = start;
+                return false;
+            }
+        }
+        true
+    }
+
     fn peek(&mut self) -> char {
         if self.is_at_end() {
             return '\0';
         }
-        self.source.chars().nth(self.current as usize).unwrap()
+        self.source[self.current as usize]
     }
 
     fn peek_next(&mut self) -> char {
-        if (self.current + 1) as usize >= self.source.chars().count() {
+        if (self.current + 1) as usize >= self.source.len() {
             return '\0';
         }
-        self.source.chars().nth((self.current + 1) as usize).unwrap()
+        self.source[(self.current + 1) as usize]
     }
 
     fn is_digit(&mut self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(&mut self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') ||
-        (c >= 'A' && c <= 'Z') ||
-        c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alpha_numeric(&mut self, c:char) -> bool {
@@ -231,7 +586,11 @@ impl<'a> Scanner<'a> {
 }
 
 #[cfg(test)]
+// The tests look tokens up with 'get(0)' and compare against owned strings, as they always have
+#[allow(clippy::get_first, clippy::cmp_owned)]
 mod scanner_tests {
+    use crate::bigint::BigInt;
+    use crate::dialect::Dialect;
     use crate::object::Object;
     use crate::scanner::Scanner;
     use crate::rlox::RLox;
@@ -315,6 +674,18 @@ mod scanner_tests {
         assert!(TokenType::Eof == result.get(1).unwrap().token_type);
     }
 
+    #[test]
+    fn test_scann_brackets() {
+        let source: String = "[]".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(TokenType::LeftBracket == result.get(0).unwrap().token_type);
+        assert!(TokenType::RightBracket == result.get(1).unwrap().token_type);
+    }
+
     #[test]
     fn test_scann_minus() {
         let source: String = "-".to_string();
@@ -387,6 +758,100 @@ mod scanner_tests {
         assert!(TokenType::Eof == result.get(1).unwrap().token_type);
     }
 
+    #[test]
+    fn test_scann_fat_arrow() {
+        let source: String = "=> == =".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![TokenType::FatArrow, TokenType::EqualEqual, TokenType::Equal, TokenType::Eof] == token_types);
+    }
+
+    #[test]
+    fn test_scann_question_and_colon() {
+        let source: String = "?:".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(TokenType::Question == result.get(0).unwrap().token_type);
+        assert!(TokenType::Colon == result.get(1).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_compound_assignment_operators() {
+        let source: String = "+= -= *= /= %= ++ -- + - * /".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::PlusEqual, TokenType::MinusEqual, TokenType::StarEqual, TokenType::SlashEqual,
+            TokenType::PercentEqual, TokenType::PlusPlus, TokenType::MinusMinus,
+            TokenType::Plus, TokenType::Minus, TokenType::Star, TokenType::Slash, TokenType::Eof
+        ] == token_types);
+    }
+
+    #[test]
+    fn test_scann_bitwise_and_exponent_operators() {
+        let source: String = "% ** & | ^ ~ << >> <= >= *".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::Percent, TokenType::StarStar, TokenType::Ampersand, TokenType::Pipe,
+            TokenType::Caret, TokenType::Tilde, TokenType::LessLess, TokenType::GreaterGreater,
+            TokenType::LessEqual, TokenType::GreaterEqual, TokenType::Star, TokenType::Eof
+        ] == token_types);
+    }
+
+    #[test]
+    fn test_scann_standard_dialect_has_no_bitwise_operators() {
+        let source: String = "<<&".to_string();
+        let mut rlox: RLox = RLox::with_dialect(Dialect::Standard);
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(rlox.had_error);
+        assert!(TokenType::Less == result.get(0).unwrap().token_type);
+        assert!(TokenType::Less == result.get(1).unwrap().token_type);
+        assert!(TokenType::Error == result.get(2).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_range_operators() {
+        let source: String = "0..10 1..=2 a.b".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::Number, TokenType::DotDot, TokenType::Number,
+            TokenType::Number, TokenType::DotDotEqual, TokenType::Number,
+            TokenType::Identifier, TokenType::Dot, TokenType::Identifier, TokenType::Eof
+        ] == token_types);
+        assert!(matches!(result.get(0).unwrap().literal, Object::Integer(0)));
+    }
+
+    #[test]
+    fn test_scann_standard_dialect_has_no_increment() {
+        let source: String = "--".to_string();
+        let mut rlox: RLox = RLox::with_dialect(Dialect::Standard);
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(TokenType::Minus == result.get(0).unwrap().token_type);
+        assert!(TokenType::Minus == result.get(1).unwrap().token_type);
+    }
+
     #[test]
     fn test_scann_equal() {
         let source: String = "=".to_string();
@@ -469,6 +934,64 @@ mod scanner_tests {
         assert!(TokenType::Eof == result.get(0).unwrap().token_type);
     }
 
+    #[test]
+    fn test_scann_block_comment() {
+        let source: String = "/* this \n should \n be ignored. */ +".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Plus == result.get(0).unwrap().token_type);
+        assert!(3 == result.get(0).unwrap().line);
+    }
+
+    #[test]
+    fn test_scann_nested_block_comment() {
+        let source: String = "/* outer /* inner */ still a comment */ -".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Minus == result.get(0).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_unterminated_block_comment() {
+        let source: String = "/* outer /* inner */".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Error == result.get(0).unwrap().token_type);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_doc_comment() {
+        let source: String = "/// First line.\n/// Second line.\nvar".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Var == result.get(0).unwrap().token_type);
+        assert!(Some("First line.\nSecond line.".to_string()) == result.get(0).unwrap().doc);
+    }
+
+    #[test]
+    fn test_scann_four_slashes_is_not_a_doc_comment() {
+        let source: String = "//// Just a comment.\nvar".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(result.get(0).unwrap().doc.is_none());
+    }
+
     #[test]
     fn test_scann_string() {
         let source: String = "\"This should be parsed as a string.\"".to_string();
@@ -486,6 +1009,96 @@ mod scanner_tests {
         assert!(Object::String(trimmed) == result.get(0).unwrap().literal);
     }
 
+    #[test]
+    fn test_scann_string_escape_sequences() {
+        let source: String = r#""tab\t newline\n quote\" backslash\\ unicode\u{1F600}""#.to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(Object::String("tab\t newline\n quote\" backslash\\ unicode\u{1F600}".to_string()) == result.get(0).unwrap().literal);
+        assert!(!rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_string_invalid_escape_sequence() {
+        let source: String = r#""bad \q escape""#.to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(Object::String("bad  escape".to_string()) == result.get(0).unwrap().literal);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_line_after_escaped_line_break() {
+        let source: String = "\"a\\\nb\"\nnext".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(rlox.had_error);
+        assert!(result.get(1).unwrap().lexeme == "next");
+        assert!(result.get(1).unwrap().line == 3);
+    }
+
+    #[test]
+    fn test_scann_string_invalid_unicode_escape() {
+        let source: String = r#""\u{D800}""#.to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        scanner.scan_tokens();
+
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_raw_string() {
+        let source: String = "r\"no \\n escapes\nhere\"".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::String == result.get(0).unwrap().token_type);
+        assert!(source == result.get(0).unwrap().lexeme);
+        assert!(Object::String("no \\n escapes\nhere".to_string()) == result.get(0).unwrap().literal);
+        assert!(scanner.line == 2);
+    }
+
+    #[test]
+    fn test_scann_raw_string_with_hashes() {
+        let source: String = r###"r#"say "hi""#"###.to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(Object::String("say \"hi\"".to_string()) == result.get(0).unwrap().literal);
+    }
+
+    #[test]
+    fn test_scann_string_interpolation() {
+        let source: String = r#""Hello ${name}, { ${ {} } }!""#.to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::Interpolation, TokenType::Identifier,
+            TokenType::Interpolation, TokenType::LeftBrace, TokenType::RightBrace,
+            TokenType::String, TokenType::Eof
+        ] == token_types);
+        assert!(Object::String("Hello ".to_string()) == result.get(0).unwrap().literal);
+        assert!(Object::String(", { ".to_string()) == result.get(2).unwrap().literal);
+        assert!(Object::String(" }!".to_string()) == result.get(5).unwrap().literal);
+    }
+
     #[test]
     fn test_scann_number() {
         let source: String = "15.28".to_string();
@@ -501,6 +1114,128 @@ mod scanner_tests {
         assert!(Object::Number(15.28) == result.get(0).unwrap().literal);
     }
 
+    #[test]
+    fn test_scann_integer_literals() {
+        let cases: Vec<(Dialect, &str, Object)> = vec![
+            (Dialect::Extended, "42", Object::Integer(42)),
+            (Dialect::Extended, "1_000", Object::Integer(1000)),
+            (Dialect::Extended, "0xFF", Object::Integer(255)),
+            (Dialect::Extended, "42.0", Object::Number(42.0)),
+            (Dialect::Extended, "4e2", Object::Number(400.0)),
+            (Dialect::Extended, "9223372036854775808", Object::BigInt(BigInt::parse("9223372036854775808", 10).unwrap())),
+            (Dialect::Extended, "0x1_0000_0000_0000_0000", Object::BigInt(BigInt::parse("10000000000000000", 16).unwrap())),
+            (Dialect::Standard, "42", Object::Number(42.0)),
+        ];
+
+        for (dialect, source, expected) in cases {
+            let mut rlox: RLox = RLox::with_dialect(dialect);
+            let mut scanner: Scanner = Scanner::new(source.to_string(), &mut rlox);
+            let result: Vec<Token> = scanner.scan_tokens();
+
+            let literal: &Object = &result.get(0).unwrap().literal;
+            assert!(std::mem::discriminant(&expected) == std::mem::discriminant(literal), "{}", source);
+            assert!(expected == *literal, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_scann_number_literal_forms() {
+        let cases: Vec<(&str, Object)> = vec![
+            ("0x1F", Object::Integer(31)),
+            ("0XfF", Object::Integer(255)),
+            ("0b1010", Object::Integer(10)),
+            ("0o17", Object::Integer(15)),
+            ("1e9", Object::Number(1e9)),
+            ("2.5e-3", Object::Number(2.5e-3)),
+            ("4E+2", Object::Number(400.0)),
+            ("1_000_000", Object::Integer(1_000_000)),
+            ("0b1111_0000", Object::Integer(240)),
+            ("12.345_6", Object::Number(12.345_6)),
+        ];
+
+        for (source, expected) in cases {
+            let mut rlox: RLox = RLox::new();
+            let mut scanner: Scanner = Scanner::new(source.to_string(), &mut rlox);
+            let result: Vec<Token> = scanner.scan_tokens();
+
+            assert!(result.len() == 2, "{}", source);
+            assert!(TokenType::Number == result.get(0).unwrap().token_type, "{}", source);
+            assert!(source == result.get(0).unwrap().lexeme, "{}", source);
+            let literal: &Object = &result.get(0).unwrap().literal;
+            assert!(std::mem::discriminant(&expected) == std::mem::discriminant(literal), "{}", source);
+            assert!(expected == *literal, "{}", source);
+            assert!(!rlox.had_error, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_scann_malformed_number_literals() {
+        let sources: Vec<&str> = vec!["0x", "0b102", "0o8", "1e", "2.5e+", "1__0", "1_", "1_.5", "0x_1"];
+
+        for source in sources {
+            let mut rlox: RLox = RLox::new();
+            let mut scanner: Scanner = Scanner::new(source.to_string(), &mut rlox);
+            scanner.scan_tokens();
+
+            assert!(rlox.had_error, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_scann_unexpected_characters_become_error_tokens() {
+        let source: String = "1 @ 2 # 3".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::Number, TokenType::Error, TokenType::Number,
+            TokenType::Error, TokenType::Number, TokenType::Eof
+        ] == token_types);
+        assert!(Object::String("Unexpected character '@'".to_string()) == result.get(1).unwrap().literal);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_unterminated_string_at_end_of_file() {
+        let source: String = "\"never closed".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Error == result.get(0).unwrap().token_type);
+        assert!(source == result.get(0).unwrap().lexeme);
+        assert!(rlox.had_error);
+    }
+
+    #[test]
+    fn test_scann_keeps_going_after_errors() {
+        let source: String = "0b2 \n @ \n var".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 4);
+        assert!(TokenType::Error == result.get(0).unwrap().token_type);
+        assert!(TokenType::Error == result.get(1).unwrap().token_type);
+        assert!(2 == result.get(1).unwrap().line);
+        assert!(TokenType::Var == result.get(2).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_non_ascii_source() {
+        let source: String = "\"héllo wörld\" /* ünïcode */ ok".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source, &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(Object::String("héllo wörld".to_string()) == result.get(0).unwrap().literal);
+        assert!("ok" == result.get(1).unwrap().lexeme);
+    }
+
     #[test]
     fn test_scann_and_keyword() {
         let source: String = "and".to_string();
@@ -600,19 +1335,57 @@ mod scanner_tests {
     }
 
     #[test]
-    fn test_scann_null_keyword() {
-        let source: String = "null".to_string();
+    fn test_scann_nil_keyword() {
+        let source: String = "nil".to_string();
         let mut rlox: RLox = RLox::new();
         let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
         let result: Vec<Token> = scanner.scan_tokens();
 
         assert!(result.len() == 2);
-        assert!(TokenType::Null == result.get(0).unwrap().token_type);
+        assert!(TokenType::Nil == result.get(0).unwrap().token_type);
         assert!(TokenType::Eof == result.get(1).unwrap().token_type);
 
         assert!(source == result.get(0).unwrap().lexeme);
     }
 
+    #[test]
+    fn test_scann_null_is_an_identifier_by_default() {
+        let source: String = "null".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Identifier == result.get(0).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_null_keyword_in_legacy_dialect() {
+        let source: String = "null nil".to_string();
+        let mut rlox: RLox = RLox::with_dialect(Dialect::Legacy);
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(TokenType::Nil == result.get(0).unwrap().token_type);
+        assert!(TokenType::Identifier == result.get(1).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_standard_dialect_has_no_extensions() {
+        let source: String = "/* 0x1F */ \"a\\n${b}\"".to_string();
+        let mut rlox: RLox = RLox::with_dialect(Dialect::Standard);
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+        let token_types: Vec<TokenType> = result.iter().map(|token| token.token_type.clone()).collect();
+
+        assert!(vec![
+            TokenType::Slash, TokenType::Star, TokenType::Number, TokenType::Identifier,
+            TokenType::Star, TokenType::Slash, TokenType::String, TokenType::Eof
+        ] == token_types);
+        assert!(Object::String("a\\n${b}".to_string()) == result.get(6).unwrap().literal);
+    }
+
     #[test]
     fn test_scann_or_keyword() {
         let source: String = "or".to_string();
@@ -725,6 +1498,39 @@ mod scanner_tests {
         assert!(source == result.get(0).unwrap().lexeme);
     }
 
+    #[test]
+    fn test_scann_break_and_continue_keywords() {
+        let source: String = "break continue".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 3);
+        assert!(TokenType::Break == result.get(0).unwrap().token_type);
+        assert!(TokenType::Continue == result.get(1).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_trait_keyword() {
+        let source: String = "trait".to_string();
+        let mut rlox: RLox = RLox::new();
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(result.len() == 2);
+        assert!(TokenType::Trait == result.get(0).unwrap().token_type);
+    }
+
+    #[test]
+    fn test_scann_break_is_an_identifier_in_standard_dialect() {
+        let source: String = "break".to_string();
+        let mut rlox: RLox = RLox::with_dialect(Dialect::Standard);
+        let mut scanner: Scanner = Scanner::new(source.clone(), &mut rlox);
+        let result: Vec<Token> = scanner.scan_tokens();
+
+        assert!(TokenType::Identifier == result.get(0).unwrap().token_type);
+    }
+
     #[test]
     fn test_scann_identifier() {
         let source: String = "some_identifier_01".to_string();
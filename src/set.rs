@@ -0,0 +1,129 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    function::NativeFunction,
+    interpreter::RuntimeError,
+    list::List,
+    object::{HashKey, Object},
+    token::Token,
+};
+
+// Elements keep their insertion order and are hashed like map keys
+#[derive(Debug, Default)]
+pub struct Set {
+    elements: Vec<Object>,
+    indices: HashMap<HashKey, usize>,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn elements(&self) -> &[Object] {
+        &self.elements
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    // Whether the element was not in the set yet
+    pub fn add(&mut self, token: &Token, element: Object) -> Result<bool, RuntimeError> {
+        let hash_key: HashKey = Self::hash_key(token, &element)?;
+        if self.indices.contains_key(&hash_key) {
+            return Ok(false);
+        }
+
+        self.indices.insert(hash_key, self.elements.len());
+        self.elements.push(element);
+        Ok(true)
+    }
+
+    pub fn contains(&self, token: &Token, element: &Object) -> Result<bool, RuntimeError> {
+        Ok(self.indices.contains_key(&Self::hash_key(token, element)?))
+    }
+
+    // Whether the element was in the set
+    pub fn remove(&mut self, token: &Token, element: &Object) -> Result<bool, RuntimeError> {
+        let Some(index) = self.indices.remove(&Self::hash_key(token, element)?) else {
+            return Ok(false);
+        };
+
+        self.elements.remove(index);
+        for later in self.indices.values_mut() {
+            if *later > index {
+                *later -= 1;
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn method(set: &Rc<RefCell<Set>>, name: &Token) -> Option<NativeFunction> {
+        let set: Rc<RefCell<Set>> = set.clone();
+
+        let method: NativeFunction = match name.lexeme.as_str() {
+            "add" => NativeFunction::new("add", 1, Rc::new(move |_, paren, mut arguments| {
+                set.borrow_mut().add(paren, arguments.remove(0))?;
+                Ok(Object::Null)
+            })),
+            "remove" => NativeFunction::new("remove", 1, Rc::new(move |_, paren, arguments| {
+                Ok(Object::Boolean(set.borrow_mut().remove(paren, &arguments[0])?))
+            })),
+            "contains" => NativeFunction::new("contains", 1, Rc::new(move |_, paren, arguments| {
+                Ok(Object::Boolean(set.borrow().contains(paren, &arguments[0])?))
+            })),
+            "len" => NativeFunction::new("len", 0, Rc::new(move |_, _, _| {
+                Ok(Object::Integer(set.borrow().len() as i64))
+            })),
+            "values" => NativeFunction::new("values", 0, Rc::new(move |_, _, _| {
+                Ok(Object::List(Rc::new(RefCell::new(List::new(set.borrow().elements.clone())))))
+            })),
+            "union" => NativeFunction::new("union", 1, Rc::new(move |_, paren, arguments| {
+                let other: Rc<RefCell<Set>> = Self::set_operand(paren, &arguments[0])?;
+                let mut result: Set = Set::new();
+                for element in set.borrow().elements.iter().chain(other.borrow().elements.iter()) {
+                    result.add(paren, element.clone())?;
+                }
+                Ok(Object::Set(Rc::new(RefCell::new(result))))
+            })),
+            "intersection" => NativeFunction::new("intersection", 1, Rc::new(move |_, paren, arguments| {
+                let other: Rc<RefCell<Set>> = Self::set_operand(paren, &arguments[0])?;
+                let mut result: Set = Set::new();
+                for element in &set.borrow().elements {
+                    if other.borrow().contains(paren, element)? {
+                        result.add(paren, element.clone())?;
+                    }
+                }
+                Ok(Object::Set(Rc::new(RefCell::new(result))))
+            })),
+            "difference" => NativeFunction::new("difference", 1, Rc::new(move |_, paren, arguments| {
+                let other: Rc<RefCell<Set>> = Self::set_operand(paren, &arguments[0])?;
+                let mut result: Set = Set::new();
+                for element in &set.borrow().elements {
+                    if !other.borrow().contains(paren, element)? {
+                        result.add(paren, element.clone())?;
+                    }
+                }
+                Ok(Object::Set(Rc::new(RefCell::new(result))))
+            })),
+            _ => return None,
+        };
+        Some(method)
+    }
+
+    fn set_operand(token: &Token, operand: &Object) -> Result<Rc<RefCell<Set>>, RuntimeError> {
+        match operand {
+            Object::Set(set) => Ok(set.clone()),
+            _ => Err(RuntimeError::new(token, "Operand must be a set.")),
+        }
+    }
+
+    fn hash_key(token: &Token, element: &Object) -> Result<HashKey, RuntimeError> {
+        element.hash_key().ok_or_else(|| RuntimeError::new(token, "Set elements must be strings, numbers, booleans or nil."))
+    }
+}
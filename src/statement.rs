@@ -0,0 +1,243 @@
+use std::{cell::OnceCell, rc::Rc};
+
+use crate::{expression::Expression, generator::{self, Instruction}, pattern::Pattern, token::Token};
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Block {
+        statements: Vec<Self>,
+    },
+    Break {
+        keyword: Token,
+    },
+    // 'traits' are the names in the 'with' clause, in source order
+    Class {
+        name: Token,
+        superclass: Option<Expression>,
+        traits: Vec<Expression>,
+        methods: Vec<Method>,
+        doc: Option<String>,
+    },
+    Continue {
+        keyword: Token,
+    },
+    Expression {
+        expression: Expression,
+    },
+    // for (name in iterable) body, 'keyword' is the 'in' token for error reporting
+    ForIn {
+        name: Token,
+        keyword: Token,
+        iterable: Expression,
+        body: Box<Self>,
+    },
+    Function {
+        declaration: Rc<FunctionDeclaration>,
+    },
+    If {
+        condition: Expression,
+        then_branch: Box<Self>,
+        else_branch: Option<Box<Self>>,
+    },
+    // match (value) { pattern if guard => body, ... }, only the first matching arm runs
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+    },
+    Print {
+        expression: Expression,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expression>,
+    },
+    Throw {
+        keyword: Token,
+        value: Expression,
+    },
+    // trait name with traits { methods }, a bundle of methods for classes to mix in
+    Trait {
+        name: Token,
+        traits: Vec<Expression>,
+        methods: Vec<Method>,
+        doc: Option<String>,
+    },
+    // At least one of 'catch' and 'finally' is present
+    Try {
+        body: Vec<Self>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Self>>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expression>,
+        doc: Option<String>,
+    },
+    // yield value; only in a function body, which makes the function a generator
+    Yield {
+        keyword: Token,
+        value: Option<Expression>,
+    },
+    // A desugared for loop keeps its increment here, so 'continue' still runs it
+    While {
+        condition: Expression,
+        body: Box<Self>,
+        increment: Option<Expression>,
+    },
+}
+
+// Shared between the syntax tree and every function object created from it
+#[derive(Debug)]
+pub struct FunctionDeclaration {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Statement>,
+    pub doc: Option<String>,
+    // The body compiled to resumable instructions when it yields
+    pub generator: Option<Rc<Vec<Instruction>>>,
+    // The body compiled so it can give way at any call, the first time it is called in a fiber
+    fiber: OnceCell<Rc<Vec<Instruction>>>,
+}
+
+impl FunctionDeclaration {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Statement>, doc: Option<String>) -> Self {
+        let generator: Option<Rc<Vec<Instruction>>> = body.iter().any(Statement::contains_yield).then(|| Rc::new(generator::compile(&body)));
+        Self { name, params, body, doc, generator, fiber: OnceCell::new() }
+    }
+
+    pub fn fiber_code(&self) -> Rc<Vec<Instruction>> {
+        self.fiber.get_or_init(|| Rc::new(generator::compile_for_fiber(&self.body))).clone()
+    }
+}
+
+// How a method in a class body is called
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MethodKind {
+    // name(params) { body }, called on instances
+    Instance,
+    // class name(params) { body }, called on the class itself
+    Static,
+    // name { body }, runs when the property is read
+    Getter,
+    // set name(value) { body }, runs when the property is assigned
+    Setter,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub kind: MethodKind,
+    pub declaration: Rc<FunctionDeclaration>,
+}
+
+// catch (name) { body }
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    pub name: Token,
+    pub body: Vec<Statement>,
+}
+
+// 'arrow' is the '=>' token for reporting on the arm
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub arrow: Token,
+    pub body: Statement,
+}
+
+pub trait StatementVisitor<R> {
+    fn visit_block_statement(&mut self, statements: &[Statement]) -> R;
+    fn visit_break_statement(&mut self, keyword: &Token) -> R;
+    fn visit_class_statement(&mut self, name: &Token, superclass: Option<&Expression>, traits: &[Expression], methods: &[Method]) -> R;
+    fn visit_continue_statement(&mut self, keyword: &Token) -> R;
+    fn visit_expression_statement(&mut self, expression: &Expression) -> R;
+    fn visit_for_in_statement(&mut self, name: &Token, keyword: &Token, iterable: &Expression, body: &Statement) -> R;
+    fn visit_function_statement(&mut self, declaration: &Rc<FunctionDeclaration>) -> R;
+    fn visit_if_statement(&mut self, condition: &Expression, then_branch: &Statement, else_branch: Option<&Statement>) -> R;
+    fn visit_match_statement(&mut self, value: &Expression, arms: &[MatchArm]) -> R;
+    fn visit_print_statement(&mut self, expression: &Expression) -> R;
+    fn visit_return_statement(&mut self, keyword: &Token, value: Option<&Expression>) -> R;
+    fn visit_throw_statement(&mut self, keyword: &Token, value: &Expression) -> R;
+    fn visit_trait_statement(&mut self, name: &Token, traits: &[Expression], methods: &[Method]) -> R;
+    fn visit_try_statement(&mut self, body: &[Statement], catch: Option<&CatchClause>, finally: Option<&[Statement]>) -> R;
+    fn visit_var_statement(&mut self, name: &Token, initializer: Option<&Expression>) -> R;
+    fn visit_while_statement(&mut self, condition: &Expression, body: &Statement, increment: Option<&Expression>) -> R;
+    fn visit_yield_statement(&mut self, keyword: &Token, value: Option<&Expression>) -> R;
+}
+
+impl Statement {
+    pub fn accept<R, T: StatementVisitor<R>>(&self, visitor: &mut T) -> R {
+        match self {
+            Statement::Block { statements } => visitor.visit_block_statement(statements),
+            Statement::Break { keyword } => visitor.visit_break_statement(keyword),
+            Statement::Class { name, superclass, traits, methods, .. } => visitor.visit_class_statement(name, superclass.as_ref(), traits, methods),
+            Statement::Continue { keyword } => visitor.visit_continue_statement(keyword),
+            Statement::Expression { expression } => visitor.visit_expression_statement(expression),
+            Statement::ForIn { name, keyword, iterable, body } => visitor.visit_for_in_statement(name, keyword, iterable, body),
+            Statement::Function { declaration } => visitor.visit_function_statement(declaration),
+            Statement::If { condition, then_branch, else_branch } => visitor.visit_if_statement(condition, then_branch, else_branch.as_deref()),
+            Statement::Match { value, arms } => visitor.visit_match_statement(value, arms),
+            Statement::Print { expression } => visitor.visit_print_statement(expression),
+            Statement::Return { keyword, value } => visitor.visit_return_statement(keyword, value.as_ref()),
+            Statement::Throw { keyword, value } => visitor.visit_throw_statement(keyword, value),
+            Statement::Trait { name, traits, methods, .. } => visitor.visit_trait_statement(name, traits, methods),
+            Statement::Try { body, catch, finally } => visitor.visit_try_statement(body, catch.as_ref(), finally.as_deref()),
+            Statement::Var { name, initializer, .. } => visitor.visit_var_statement(name, initializer.as_ref()),
+            Statement::While { condition, body, increment } => visitor.visit_while_statement(condition, body, increment.as_ref()),
+            Statement::Yield { keyword, value } => visitor.visit_yield_statement(keyword, value.as_ref()),
+        }
+    }
+
+    // Whether a yield is in this statement, not counting nested functions which are generators of their own
+    pub fn contains_yield(&self) -> bool {
+        self.contains(&|statement| matches!(statement, Statement::Yield { .. }))
+    }
+
+    // Whether a call is in this statement, a fiber can only give way inside one
+    pub fn contains_call(&self) -> bool {
+        self.contains(&|statement| statement.expressions().into_iter().any(Expression::contains_call))
+    }
+
+    // Whether a break, continue or return is in this statement, leaving it or not
+    pub fn can_exit(&self) -> bool {
+        self.contains(&|statement| matches!(statement, Statement::Break { .. } | Statement::Continue { .. } | Statement::Return { .. }))
+    }
+
+    // Whether this statement or one nested in it matches, function and class bodies are left out
+    fn contains(&self, predicate: &dyn Fn(&Statement) -> bool) -> bool {
+        if predicate(self) {
+            return true;
+        }
+
+        let any = |statements: &[Statement]| statements.iter().any(|statement| statement.contains(predicate));
+        match self {
+            Statement::Block { statements } => any(statements),
+            Statement::ForIn { body, .. } | Statement::While { body, .. } => body.contains(predicate),
+            Statement::If { then_branch, else_branch, .. } => {
+                then_branch.contains(predicate) || else_branch.as_ref().is_some_and(|else_branch| else_branch.contains(predicate))
+            },
+            Statement::Match { arms, .. } => arms.iter().any(|arm| arm.body.contains(predicate)),
+            Statement::Try { body, catch, finally } => {
+                any(body) || catch.as_ref().is_some_and(|catch| any(&catch.body)) || finally.as_ref().is_some_and(|finally| any(finally))
+            },
+            _ => false,
+        }
+    }
+
+    // The expressions that belong to this statement itself, not to the statements nested in it
+    fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Statement::Expression { expression } | Statement::Print { expression } => vec![expression],
+            Statement::ForIn { iterable, .. } => vec![iterable],
+            Statement::If { condition, .. } => vec![condition],
+            Statement::Match { value, arms } => std::iter::once(value).chain(arms.iter().filter_map(|arm| arm.guard.as_ref())).collect(),
+            Statement::Return { value, .. } | Statement::Yield { value, .. } => value.iter().collect(),
+            Statement::Throw { value, .. } => vec![value],
+            Statement::Var { initializer, .. } => initializer.iter().collect(),
+            Statement::While { condition, increment, .. } => std::iter::once(condition).chain(increment).collect(),
+            Statement::Class { superclass, traits, .. } => superclass.iter().chain(traits).collect(),
+            Statement::Trait { traits, .. } => traits.iter().collect(),
+            Statement::Block { .. } | Statement::Break { .. } | Statement::Continue { .. } | Statement::Function { .. } | Statement::Try { .. } => Vec::new(),
+        }
+    }
+}
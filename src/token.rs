@@ -5,7 +5,9 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Object,
-    pub line: usize
+    pub line: usize,
+    // `///` doc comments that directly precede this token
+    pub doc: Option<String>
 }
 
 impl Token {
@@ -14,7 +16,8 @@ impl Token {
             token_type,
             lexeme,
             literal,
-            line
+            line,
+            doc: None
         }
     }
 }
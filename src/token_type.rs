@@ -1,21 +1,32 @@
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
   // Single-character tokens.
-  LeftParen, RightParen, LeftBrace, RightBrace,
+  LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
   Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+  Question, Colon, Percent, Ampersand, Pipe, Caret, Tilde,
 
   // One or two character tokens.
   Bang, BangEqual,
   Equal, EqualEqual,
   Greater, GreaterEqual,
   Less, LessEqual,
+  PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual,
+  PlusPlus, MinusMinus,
+  StarStar, LessLess, GreaterGreater,
+  DotDot, DotDotEqual, FatArrow,
 
   // Literals.
   Identifier, String, Number,
+  // A string segment followed by an interpolated expression.
+  Interpolation,
 
   // Keywords.
-  And, Class, Else, False, Fun, For, If, Null, Or,
+  And, Class, Else, False, Fun, For, If, Nil, Or,
   Print, Return, Super, This, True, Var, While,
+  Break, Continue, In, Throw, Try, Catch, Finally, Match, Trait, Yield,
+
+  // A lexical error, the literal holds the message.
+  Error,
 
   Eof
 }